@@ -0,0 +1,369 @@
+// computer players: they read the scene and answer with the actions a human
+// would be holding, so they play by the same rules and end up in replays as
+// ordinary input
+use art_stamps::F64Point;
+use std::collections::VecDeque;
+use super::collision::Aabb;
+use super::game::{SceneState, LocationVel, MAX_SPEED};
+use super::input::{Actor, Action, Actions};
+use super::nav::{NavGraph, NavMove};
+
+// a new route is worked out this often, in ticks
+const REPLAN_TICKS: u32 = 12;
+// how close, in pixels, the feet have to come to a node to have reached it
+const ARRIVE_X: f64 = 6.0;
+const ARRIVE_Y: f64 = 24.0;
+// closer than this to where it is heading, an actor stops pressing towards it
+const DEAD_ZONE: f64 = 2.0;
+// how far, in its own sizes, the cat can reach from the end of its route
+const POUNCE_REACH: f64 = 1.0;
+
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    // ticks between something happening and the computer player noticing
+    fn reaction_ticks(self) -> usize {
+        match self {
+            Difficulty::Easy => 36,
+            Difficulty::Normal => 18,
+            Difficulty::Hard => 4,
+        }
+    }
+    // fraction of the top running speed it allows itself
+    fn speed(self) -> f32 {
+        match self {
+            Difficulty::Easy => 0.6,
+            Difficulty::Normal => 0.8,
+            Difficulty::Hard => 1.0,
+        }
+    }
+}
+
+impl std::str::FromStr for Difficulty {
+    type Err = String;
+    fn from_str(name: &str) -> Result<Self, String> {
+        match name {
+            "easy" => Ok(Difficulty::Easy),
+            "normal" => Ok(Difficulty::Normal),
+            "hard" => Ok(Difficulty::Hard),
+            _ => Err(format!("unknown difficulty {}, expected easy, normal or hard", name)),
+        }
+    }
+}
+
+// steers one actor along routes through the navigation graph
+#[derive(Clone,Debug)]
+struct Follower {
+    difficulty: Difficulty,
+    // nodes still to reach, with the move into each
+    route: VecDeque<(usize, NavMove)>,
+    // the node the current move started from
+    from: Option<usize>,
+    // whether the route ends at the goal, rather than as close as it gets
+    reaches_goal: bool,
+    replan_in: u32,
+    jump_held: bool,
+    // whether to keep heading for a goal the route falls short of, jumping at
+    // whatever is in the way, rather than waiting as close as it gets
+    presses_on: bool,
+}
+
+impl Follower {
+    fn new(difficulty: Difficulty, presses_on: bool) -> Self {
+        Follower{
+            difficulty,
+            route:VecDeque::new(),
+            from:None,
+            reaches_goal:false,
+            replan_in:0,
+            jump_held:false,
+            presses_on,
+        }
+    }
+    // forgets the route, which is made of nodes of a graph that may be gone
+    fn reset(&mut self) {
+        *self = Follower::new(self.difficulty, self.presses_on);
+    }
+    // whether the next steer works out a new route
+    fn replans(&self, body: &LocationVel) -> bool {
+        self.replan_in <= 1 && body.contacts.grounded
+    }
+    // the actions that take body towards goal, planning a route there through
+    // nav every so often; tolls are extra costs of passing through nodes, by
+    // index, with nodes past its end free
+    fn steer(&mut self, nav: &NavGraph, body: &LocationVel, goal: F64Point, tolls: &[f64]) -> Actions {
+        let size = size(body);
+        let speed_limit = MAX_SPEED * self.difficulty.speed();
+        let feet = feet(body);
+        self.replan_in = self.replan_in.saturating_sub(1);
+        // mid-air, the route being flown is the only one that makes sense
+        if self.replan_in == 0 && body.contacts.grounded {
+            self.replan_in = REPLAN_TICKS;
+            self.from = nav.nearest(feet, size);
+            let to = nav.nearest(goal, size);
+            self.route = match (self.from, to) {
+                (Some(from), Some(to)) => nav.path(from, to, size, f64::from(speed_limit), |node| tolls.get(node).copied().unwrap_or(0.)).into(),
+                _ => VecDeque::new(),
+            };
+            let end = self.route.back().map(|&(node, _)| node).or(self.from);
+            self.reaches_goal = end.is_none() || end == to;
+        }
+        // skip past the nodes already reached
+        while let Some(&(node, _)) = self.route.front() {
+            let position = nav.nodes()[node].position;
+            if !body.contacts.grounded || (position.0 - feet.0).abs() > ARRIVE_X || (position.1 - feet.1).abs() > ARRIVE_Y {
+                break;
+            }
+            self.from = Some(node);
+            self.route.pop_front();
+        }
+        let mut actions = Actions::default();
+        let (target, jump) = match self.route.front() {
+            Some(&(node, kind)) => {
+                let at_takeoff = self.from.is_some_and(|from| (nav.nodes()[from].position.0 - feet.0).abs() <= ARRIVE_X);
+                (nav.nodes()[node].position, kind == NavMove::Jump && at_takeoff)
+            },
+            // off the graph or already there: head straight for the goal
+            None if self.reaches_goal || self.presses_on => (goal, goal.1 < feet.1 - size),
+            // as close as it gets: wait there
+            None => (feet, false),
+        };
+        let direction = target.0 - feet.0;
+        if direction.abs() > DEAD_ZONE {
+            // pressing only while below the speed limit keeps it there
+            if direction < 0. && body.vx > -speed_limit {
+                actions.insert(Action::Left);
+            } else if direction > 0. && body.vx < speed_limit {
+                actions.insert(Action::Right);
+            }
+        }
+        // up and over anything in the way
+        let blocked = (direction < 0. && body.contacts.wall_left) || (direction > 0. && body.contacts.wall_right);
+        if (jump || blocked) && body.contacts.grounded && !self.jump_held {
+            actions.insert(Action::Jump);
+        }
+        self.jump_held = actions.contains(Action::Jump);
+        actions
+    }
+}
+
+// chases the mouse
+#[derive(Clone,Debug)]
+pub struct CatAi {
+    follower: Follower,
+    // where the mouse has been lately, oldest first; the cat goes after the
+    // oldest, which is how long it takes to react
+    seen: VecDeque<F64Point>,
+}
+
+impl CatAi {
+    pub fn new(difficulty: Difficulty) -> Self {
+        CatAi{
+            // the graph misses some ways up, like climbing onto a ledge it only
+            // partly stands on, which trying anyway finds
+            follower:Follower::new(difficulty, true),
+            seen:VecDeque::new(),
+        }
+    }
+    // starts over after the level changed
+    pub fn reset(&mut self) {
+        self.follower.reset();
+        self.seen.clear();
+    }
+    pub fn think(&mut self, scene: &mut SceneState) -> Result<Actions, String> {
+        let goal = notice(&mut self.seen, feet(scene.actor(Actor::Mouse)), self.follower.difficulty);
+        let body = scene.actor(Actor::Cat).clone();
+        let nav = scene.nav()?;
+        Ok(self.follower.steer(nav, &body, goal, &[]))
+    }
+}
+
+// runs from the cat: out through a goal when it can get there first, and
+// otherwise to the place furthest from the cat that it can still get to
+// first, which is often through a gap too small for the cat to follow; either
+// way by a route that keeps out of the cat's reach all along
+#[derive(Clone,Debug)]
+pub struct MouseAi {
+    follower: Follower,
+    // where the cat has been lately, oldest first
+    seen: VecDeque<F64Point>,
+    // where it is heading, worked out again only when the route is
+    target: Option<F64Point>,
+    // the route's tolls, which rule out the nodes the cat could cut it off at
+    tolls: Vec<f64>,
+}
+
+impl MouseAi {
+    pub fn new(difficulty: Difficulty) -> Self {
+        MouseAi{
+            follower:Follower::new(difficulty, false),
+            seen:VecDeque::new(),
+            target:None,
+            tolls:Vec::new(),
+        }
+    }
+    // starts over after the level changed
+    pub fn reset(&mut self) {
+        self.follower.reset();
+        self.seen.clear();
+        self.target = None;
+        self.tolls.clear();
+    }
+    pub fn think(&mut self, scene: &mut SceneState) -> Result<Actions, String> {
+        let cat_feet = notice(&mut self.seen, feet(scene.actor(Actor::Cat)), self.follower.difficulty);
+        let cat_size = size(scene.actor(Actor::Cat));
+        let body = scene.actor(Actor::Mouse).clone();
+        let goals: Vec<Aabb> = scene.goals().iter().map(|goal| Aabb::around(goal)).collect();
+        let nav = scene.nav()?;
+        let target = match self.target {
+            Some(target) if !self.follower.replans(&body) => target,
+            _ => {
+                let (target, tolls) = self.choose_target(nav, &body, &goals, cat_feet, cat_size);
+                self.tolls = tolls;
+                target
+            },
+        };
+        self.target = Some(target);
+        Ok(self.follower.steer(nav, &body, target, &self.tolls))
+    }
+    // where to head for, and the tolls that keep the route there away from
+    // the cat
+    fn choose_target(&self, nav: &NavGraph, body: &LocationVel, goals: &[Aabb], cat_feet: F64Point, cat_size: f64) -> (F64Point, Vec<f64>) {
+        let size = size(body);
+        let speed = f64::from(MAX_SPEED * self.follower.difficulty.speed());
+        let from = match nav.nearest(feet(body), size) {
+            Some(from) => from,
+            None => return (feet(body), Vec::new()),
+        };
+        let ours = nav.costs(from, size, speed, |_| 0.);
+        // the cat's, scaled to the distance the mouse covers in the same time
+        let scale = speed / f64::from(MAX_SPEED);
+        let theirs = cat_costs(nav, cat_feet, cat_size).iter().map(|cost| cost * scale).collect::<Vec<f64>>();
+        // a node the cat gets to first, or so soon after that it can pounce,
+        // is no way through; that includes wherever the cat is standing
+        let margin = cat_size * POUNCE_REACH * scale;
+        let tolls: Vec<f64> = (0..ours.len())
+            .map(|node| if theirs[node] - ours[node] > margin { 0. } else { f64::INFINITY })
+            .collect();
+        // the costs going only through those the cat can't cut off
+        let clear = nav.costs(from, size, speed, |node| tolls[node]);
+        let safe = || (0..clear.len()).filter(|&node| clear[node].is_finite() && theirs[node] - clear[node] > margin);
+        let way_out = safe()
+            .filter(|&node| goals.iter().any(|goal| goal.expanded(size).contains(nav.nodes()[node].position)))
+            .min_by(|&a, &b| clear[a].total_cmp(&clear[b]));
+        // where the cat takes longest to get, or the nearest of the places it
+        // can't get to at all
+        let hideout = safe().max_by(|&a, &b| theirs[a].total_cmp(&theirs[b]).then(clear[b].total_cmp(&clear[a])));
+        if let Some(node) = way_out.or(hideout) {
+            return (nav.nodes()[node].position, tolls);
+        }
+        // cornered: wherever it stays the least behind, by any way there
+        let ahead = |node: usize| theirs[node] - ours[node];
+        let cornered = (0..ours.len()).filter(|&node| ours[node].is_finite()).max_by(|&a, &b| ahead(a).total_cmp(&ahead(b)));
+        (cornered.map(|node| nav.nodes()[node].position).unwrap_or_else(|| feet(body)), Vec::new())
+    }
+}
+
+// how far the cat has to go to get to each node; a node it can't reach counts
+// as reached when it can get within a pounce of it, and as out of its reach
+// otherwise
+fn cat_costs(nav: &NavGraph, cat_feet: F64Point, cat_size: f64) -> Vec<f64> {
+    let mut costs = match nav.nearest(cat_feet, cat_size) {
+        Some(cat) => nav.costs(cat, cat_size, f64::from(MAX_SPEED), |_| 0.),
+        None => vec![f64::INFINITY; nav.nodes().len()],
+    };
+    let mut closest: Vec<(F64Point, f64)> = (0..costs.len()).filter(|&node| costs[node].is_finite())
+        .map(|node| (nav.nodes()[node].position, costs[node])).collect();
+    if closest.is_empty() {
+        closest.push((cat_feet, 0.));
+    }
+    let reach = cat_size * POUNCE_REACH;
+    for (node, cost) in costs.iter_mut().enumerate().filter(|(_, cost)| cost.is_infinite()) {
+        let position = nav.nodes()[node].position;
+        *cost = closest.iter()
+            .map(|&(from, from_cost)| (from_cost, ((position.0 - from.0).powi(2) + (position.1 - from.1).powi(2)).sqrt()))
+            .filter(|&(_, distance)| distance <= reach)
+            .map(|(from_cost, distance)| from_cost + distance)
+            .fold(f64::INFINITY, f64::min);
+    }
+    costs
+}
+
+// remembers point as the latest sighting and answers with the one difficulty
+// allows reacting to by now
+fn notice(seen: &mut VecDeque<F64Point>, point: F64Point, difficulty: Difficulty) -> F64Point {
+    seen.push_back(point);
+    while seen.len() > difficulty.reaction_ticks() + 1 {
+        seen.pop_front();
+    }
+    seen[0]
+}
+
+// the middle of the bottom of the body
+fn feet(body: &LocationVel) -> F64Point {
+    let bounds = body.bounds();
+    ((bounds.min.0 + bounds.max.0) * 0.5, bounds.max.1)
+}
+
+fn size(body: &LocationVel) -> f64 {
+    body.bounds().max.1 - body.bounds().min.1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::game::{RoundState, CAT_SIZE, MOUSE_SIZE, TICKS_PER_SECOND};
+    use super::super::input::Input;
+    use super::super::level::{fixtures, CAT_SPAWN_STAMP, GOAL_STAMP, MOUSE_SPAWN_STAMP};
+    use std::fs::read_to_string;
+
+    // whether a hard cat catches a mouse that never moves within a round
+    fn catches_idle_mouse(path: &str) -> bool {
+        let mut scene = fixtures::scene(&read_to_string(path).unwrap(), 1);
+        let mut cat = CatAi::new(Difficulty::Hard);
+        loop {
+            let input = Input{cat:cat.think(&mut scene).unwrap(), ..Input::default()};
+            scene.sim(&input).unwrap();
+            match scene.round() {
+                RoundState::Caught{..} => return true,
+                RoundState::Countdown{..} | RoundState::Playing{..} => {},
+                _ => return false,
+            }
+        }
+    }
+
+    #[test]
+    fn a_hard_cat_catches_an_idle_mouse() {
+        for path in ["assets/level.svg", "assets/attic.svg"].iter() {
+            assert!(catches_idle_mouse(path), "the cat never got to the mouse in {}", path);
+        }
+    }
+
+    #[test]
+    fn a_hard_mouse_keeps_clear_of_an_idle_cat() {
+        // the way out is down a tunnel too low for the cat, which stands
+        // between it and the mouse
+        let mut body = String::new();
+        for x in (0..1024).step_by(64) {
+            body += &fixtures::stamp("square", f64::from(x), 640., 64);
+            if x >= 640 {
+                body += &fixtures::stamp("square", f64::from(x), 528., 64);
+            }
+        }
+        body += &fixtures::stamp(MOUSE_SPAWN_STAMP, 100., 600., MOUSE_SIZE);
+        body += &fixtures::stamp(CAT_SPAWN_STAMP, 480., 570., CAT_SIZE);
+        body += &fixtures::stamp(GOAL_STAMP, 900., 600., MOUSE_SIZE);
+        let mut scene = fixtures::scene(&fixtures::level_data(1024, 768, &body), 1);
+        let mut mouse = MouseAi::new(Difficulty::Hard);
+        for _ in 0..TICKS_PER_SECOND * 15 {
+            let input = Input{mouse:mouse.think(&mut scene).unwrap(), ..Input::default()};
+            scene.sim(&input).unwrap();
+            assert!(!matches!(scene.round(), RoundState::Caught{..}), "ran into the cat at {:?}", scene.actor(Actor::Mouse).center());
+        }
+    }
+}
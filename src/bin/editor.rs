@@ -0,0 +1,135 @@
+// building levels inside the game: stamps from the inventory are placed,
+// turned, sized and tinted at the pointer, and the level written back out
+use mousegame::{Transform, F64Point, Color, Aabb, SceneState, History, centered_at, new_stamp, CAT_SPAWN_STAMP, MOUSE_SPAWN_STAMP, GOAL_STAMP, CAT_SIZE, MOUSE_SIZE};
+use art_stamps::g;
+use std::path::Path;
+use super::view::Images;
+
+// stamps snap so their corners sit on this grid, in pixels
+const SNAP_GRID: f64 = 16.0;
+const SCALES: [f64; 5] = [0.5, 1.0, 1.5, 2.0, 3.0];
+const PALETTE: [Color; 6] = [
+    Color{r:0, g:0, b:0},
+    Color{r:128, g:128, b:128},
+    Color{r:128, g:64, b:0},
+    Color{r:0, g:128, b:0},
+    Color{r:0, g:0, b:160},
+    Color{r:160, g:0, b:0},
+];
+const GOAL_SIZE: u32 = 64;
+// how far the view moves per tick while editing
+pub const PAN_SPEED: f64 = 8.0;
+
+// something that can be placed: a stamp image, or a spawn or goal marker
+#[derive(Clone, Debug)]
+pub struct InventoryItem {
+    // into Images::stamps; None for markers
+    pub stamp_index: Option<usize>,
+    pub stamp_name: String,
+    // unscaled size in pixels
+    pub width: u32,
+    pub height: u32,
+}
+
+// the stamp images, then the markers
+pub fn inventory(images: &Images) -> Vec<InventoryItem> {
+    let mut names: Vec<(&String, &usize)> = images.inventory_map.iter().collect();
+    names.sort();
+    let stamps = names.into_iter().map(|(name, &index)| {
+        let surface = &images.stamps[index].surface;
+        InventoryItem{stamp_index:Some(index), stamp_name:name.clone(), width:surface.width(), height:surface.height()}
+    });
+    // markers as big as what they mark
+    let markers = [(CAT_SPAWN_STAMP, CAT_SIZE), (MOUSE_SPAWN_STAMP, MOUSE_SIZE), (GOAL_STAMP, GOAL_SIZE)].iter().map(
+        |&(marker, size)| InventoryItem{stamp_index:None, stamp_name:format!("{}.bmp", marker), width:size, height:size});
+    stamps.chain(markers).collect()
+}
+
+pub struct Editor {
+    // while set, the game is paused and clicks edit the level
+    pub active: bool,
+    pub inventory: Vec<InventoryItem>,
+    selected: usize,
+    // degrees, in quarter turns like the stamps of the shipped levels
+    rotate: f64,
+    scale: usize,
+    fill: usize,
+    pub snap: bool,
+    // the middle of the view
+    pub focus: F64Point,
+    // what was done to the level, for undoing
+    pub history: History,
+}
+
+impl Editor {
+    pub fn new() -> Self {
+        Editor{
+            active:false,
+            inventory:Vec::new(),
+            selected:0,
+            rotate:0.,
+            scale:1,
+            fill:0,
+            snap:true,
+            focus:(0., 0.),
+            history:History::default(),
+        }
+    }
+    pub fn selected(&self) -> Option<&InventoryItem> {
+        self.inventory.get(self.selected)
+    }
+    pub fn select_next(&mut self, step: isize) {
+        if !self.inventory.is_empty() {
+            let count = self.inventory.len() as isize;
+            self.selected = (self.selected as isize + step).rem_euclid(count) as usize;
+        }
+    }
+    pub fn rotate(&mut self) {
+        self.rotate = (self.rotate + 90.) % 360.;
+    }
+    pub fn resize(&mut self, step: isize) {
+        self.scale = (self.scale as isize + step).clamp(0, SCALES.len() as isize - 1) as usize;
+    }
+    pub fn next_fill(&mut self) {
+        self.fill = (self.fill + 1) % PALETTE.len();
+    }
+    // where the selected item goes when placed at point
+    pub fn placement(&self, point: F64Point) -> Option<Transform> {
+        let item = self.selected()?;
+        let mut transform = centered_at(point, item.width, item.height);
+        transform.rotate = self.rotate;
+        transform.scale = SCALES[self.scale];
+        if self.snap {
+            // snap the corner of the box the stamp covers, which quarter
+            // turns keep axis aligned
+            let bounds = Aabb::around(&transform.to_bbox());
+            transform.tx += snap(bounds.min.0) - bounds.min.0;
+            transform.ty += snap(bounds.min.1) - bounds.min.1;
+        }
+        Some(transform)
+    }
+    // the selected item as a stamp placed at point, its image in the stamps
+    // directory of assets
+    pub fn stamp_at(&self, point: F64Point, assets: &Path) -> Option<g> {
+        let item = self.selected()?;
+        let url = assets.join("stamps").join(&item.stamp_name);
+        Some(new_stamp(self.placement(point)?, &url.to_string_lossy(), PALETTE[self.fill]))
+    }
+    pub fn fill(&self) -> Color {
+        PALETTE[self.fill]
+    }
+}
+
+fn snap(value: f64) -> f64 {
+    (value / SNAP_GRID).round() * SNAP_GRID
+}
+
+// what lies under point, markers being on top of the stamps: whether it is a
+// marker, and its index among the markers or stamps
+pub fn hit(scene: &SceneState, point: F64Point) -> Option<(bool, usize)> {
+    let covers = |stamp: &g| Aabb::around(&stamp.transform.to_bbox()).contains(point);
+    if let Some(index) = scene.markers().iter().rposition(covers) {
+        return Some((true, index));
+    }
+    scene.svg().stamps.iter().rposition(covers).map(|index| (false, index))
+}
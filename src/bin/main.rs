@@ -3,14 +3,61 @@ use sdl2::keyboard::Keycode;
 
 use std::collections::HashMap;
 
+// more ticks than this in one frame means we fell behind; drop them instead of spiralling
+const MAX_TICKS_PER_FRAME: u32 = 8;
+
+// accumulates wall clock time and hands it out as whole simulation ticks
+pub struct FixedTimestep {
+    last_frame: Option<std::time::Instant>,
+    accumulator: std::time::Duration,
+    tick: std::time::Duration,
+}
+
+impl FixedTimestep {
+    pub fn new(tick_seconds: f32) -> Self {
+        FixedTimestep{
+            last_frame:None,
+            accumulator:std::time::Duration::default(),
+            tick:std::time::Duration::from_secs_f32(tick_seconds),
+        }
+    }
+    // returns how many ticks to simulate since the last call
+    pub fn advance(&mut self) -> u32 {
+        let now = std::time::Instant::now();
+        if let Some(last_frame) = self.last_frame {
+            self.accumulator += now - last_frame;
+        }
+        self.last_frame = Some(now);
+        let mut ticks = 0;
+        while self.accumulator >= self.tick {
+            self.accumulator -= self.tick;
+            ticks += 1;
+        }
+        if ticks > MAX_TICKS_PER_FRAME {
+            ticks = MAX_TICKS_PER_FRAME;
+        }
+        ticks
+    }
+    // fraction of a tick that has elapsed past the latest simulated state
+    pub fn alpha(&self) -> f64 {
+        self.accumulator.as_secs_f64() / self.tick.as_secs_f64()
+    }
+    pub fn until_next_tick(&self) -> std::time::Duration {
+        self.tick - self.accumulator
+    }
+}
+
+// one frame of the game, called over and over by the platform's main loop
+pub type MainLoop<'b> = fn(sdl_context: &sdl2::Sdl, scene_state: &mut super::GameView, canvas: &mut sdl2::render::Canvas<sdl2::video::Window>, images: &mut super::Images<'b>, keys_down: &mut HashMap<Keycode, ()>, texture_creator:&'b sdl2::render::TextureCreator<sdl2::video::WindowContext>) -> Result<(), String>;
+
 pub struct MainLoopArg<'a, 'b>{
     pub sdl_context: &'a sdl2::Sdl,
-    pub scene_state: &'a mut super::SceneState,
+    pub scene_state: &'a mut super::GameView,
     pub canvas: &'a mut sdl2::render::Canvas<sdl2::video::Window>,
     pub images: &'a mut super::Images<'b>,
     pub keys_down: &'a mut HashMap<Keycode, ()>,
     pub texture_creator:&'b sdl2::render::TextureCreator<sdl2::video::WindowContext>,
-    pub main_loop: fn(sdl_context: &sdl2::Sdl, scene_state: &mut super::SceneState, canvas: &mut sdl2::render::Canvas<sdl2::video::Window>, images: &mut super::Images<'b>, keys_down: &mut HashMap<Keycode, ()>, texture_creator:&'b sdl2::render::TextureCreator<sdl2::video::WindowContext>) -> Result<(), String>
+    pub main_loop: MainLoop<'b>,
 }
 
 #[cfg(not(any(target_arch = "wasm32", target_arch = "asmjs")))]
@@ -1,10 +1,16 @@
+// main.rs holds the frame loop rather than being a binary of its own
+#![allow(special_module_name)]
 extern crate sdl2;
 extern crate art_stamps;
+extern crate mousegame;
 mod main;
-mod game;
-pub use art_stamps::{SVG, HrefAndClipMask};
-pub use game::{SceneState, Images, TextureSurface};
-use std::time;
+mod options;
+mod view;
+mod editor;
+pub use art_stamps::SVG;
+pub use mousegame::{SceneState, Level, Replay, Campaign, CatAi, MouseAi, Controls, stamp_file_name};
+pub use options::{Options, Renderer};
+pub use view::{GameView, Images, TextureSurface};
 use std::string::String;
 use std::collections::HashMap;
 use std::path::Path;
@@ -12,14 +18,13 @@ use std::io;
 use std::io::{Read,Write};
 use std::fs;
 use sdl2::event::Event;
-use sdl2::rect::{Rect,Point};
 use sdl2::keyboard::Keycode;
 use sdl2::mouse::Cursor;
 use sdl2::surface::Surface;
 
-static DESIRED_DURATION_PER_FRAME:time::Duration = time::Duration::from_millis(1);
-static START_DURATION_PER_FRAME:time::Duration = time::Duration::from_millis(200);
-static DELTA_DURATION_PER_FRAME:time::Duration = time::Duration::from_millis(75);
+// the mouse id SDL gives the mouse events it makes up from touches, which the
+// touch buttons handle instead
+const TOUCH_MOUSE_ID: u32 = u32::MAX;
 
 macro_rules! make_texture_surface {
     ($texture_creator: expr, $surf: expr, $name: expr) => (match $texture_creator.create_texture_from_surface(&$surf) {
@@ -34,30 +39,54 @@ macro_rules! make_texture_surface {
 
 
 
-fn process(state: &mut SceneState, _images: &mut Images, event: sdl2::event::Event, keys_down: &mut HashMap<Keycode, ()>) -> Result<bool,String>{
+fn process(state: &mut GameView, _images: &mut Images, event: sdl2::event::Event, keys_down: &mut HashMap<Keycode, ()>) -> Result<bool,String>{
     let mut key_encountered = false;
     match event {
         Event::Quit{..} => {
             return Err("Exit".to_string())
         },
         Event::KeyDown {keycode: Option::Some(key_code), ..} =>{
-            let repeat;
-             if let None = keys_down.insert(key_code, ()) {
-                repeat = false;
-            } else {
+            if keys_down.insert(key_code, ()).is_some() {
                 return Ok(false);
             }
+            let repeat = false;
             key_encountered = true;
-            state.apply_keys(&keys_down, Some(key_code), repeat);
+            state.apply_keys(keys_down, Some(key_code), repeat);
         },
         Event::KeyUp {keycode: Option::Some(key_code), ..} =>
         {
             keys_down.remove(&key_code);
+            state.apply_keys(keys_down, None, false);
         },
-        Event::MouseButtonDown {x, y, ..} => {
+        Event::ControllerDeviceAdded {which, ..} => {
+            state.add_controller(which)?;
+        }
+        Event::ControllerDeviceRemoved {which, ..} => {
+            state.remove_controller(which);
+        }
+        Event::ControllerButtonDown {which, button, ..} => {
+            state.controller_button(which, button, true);
+        }
+        Event::ControllerButtonUp {which, button, ..} => {
+            state.controller_button(which, button, false);
+        }
+        Event::ControllerAxisMotion {which, axis, value, ..} => {
+            state.controller_axis(which, axis, value);
+        }
+        Event::FingerDown {finger_id, x, y, ..} | Event::FingerMotion {finger_id, x, y, ..} => {
+            state.touch(finger_id, x, y);
+        }
+        Event::FingerUp {finger_id, ..} => {
+            state.untouch(finger_id);
+        }
+        Event::MouseButtonDown {which: TOUCH_MOUSE_ID, ..} | Event::MouseButtonUp {which: TOUCH_MOUSE_ID, ..} => {}
+        Event::MouseButtonDown {x, y, mouse_btn, ..} => {
             state.cursor_x = x;
             state.cursor_y = y;
-            state.click();
+            state.pointer_button(mouse_btn, true);
+        }
+        Event::MouseButtonUp {mouse_btn, ..} => {
+            state.pointer_button(mouse_btn, false);
         }
         Event::MouseMotion {x, y, ..} => {
             state.cursor_x = x;
@@ -84,24 +113,142 @@ fn read_to_string(filename: &Path) ->  Result<String, io::Error> {
     Ok(buffer)
 }
 
-pub fn run(dir: &Path) -> Result<(), String> {
+// reads a level, also returning the file contents so replays can check they match
+fn load_level(path: &Path) -> Result<(String, Level), String> {
+    let level_data = read_to_string(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+    let svg = SVG::from_str(&level_data).map_err(|err| format!("{}: {:?}", path.display(), err))?;
+    Ok((level_data, Level::from_svg(svg)))
+}
+
+// the levels to play: just --level if given, else those listed in the asset
+// directory's campaign.xml, else its lone level.svg
+fn load_campaign(options: &Options) -> Result<Campaign, String> {
+    if options.level.is_some() {
+        return Ok(Campaign::new(vec![options.level_path().to_string_lossy().into_owned()]));
+    }
+    let manifest_path = options.assets.join("campaign.xml");
+    let mut campaign = match read_to_string(&manifest_path) {
+        Ok(manifest) => manifest.parse::<Campaign>().map_err(|err| format!("{}: {}", manifest_path.display(), err))?,
+        Err(_) => Campaign::new(vec!["level.svg".to_string()]),
+    };
+    // manifest entries are relative to the asset directory
+    for level in campaign.levels.iter_mut() {
+        *level = options.assets.join(&*level).to_string_lossy().into_owned();
+    }
+    Ok(campaign)
+}
+
+// the key bindings in the asset directory's controls.xml, or the defaults
+// when there is none
+fn load_controls(options: &Options) -> Result<Controls, String> {
+    let path = options.assets.join("controls.xml");
+    match read_to_string(&path) {
+        Ok(config) => config.parse::<Controls>().map_err(|err| format!("{}: {}", path.display(), err)),
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => Ok(Controls::default()),
+        Err(err) => Err(format!("{}: {}", path.display(), err)),
+    }
+}
+
+// moves the game on to another level of the campaign
+fn change_level(state: &mut GameView, index: usize) -> Result<(), String> {
+    let (_, level) = load_level(Path::new(&state.campaign.levels[index]))?;
+    state.scene.load_level(level);
+    state.scene.load_polygons(&state.assets)?;
+    state.editor.history.clear();
+    if let Some(ref mut cat_ai) = state.cat_ai {
+        cat_ai.reset();
+    }
+    if let Some(ref mut mouse_ai) = state.mouse_ai {
+        mouse_ai.reset();
+    }
+    eprintln!("LEVEL {} {}", index, state.campaign.levels[index]);
+    Ok(())
+}
+
+fn new_seed() -> u64 {
+    std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map(|d| d.as_nanos() as u64).unwrap_or(0)
+}
+
+fn load_replay(path: &Path) -> Result<Replay, String> {
+    let data = read_to_string(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+    data.parse::<Replay>()
+}
+
+// re-simulates a replay without opening a window
+fn replay_headless(options: &Options, replay: &Replay) -> Result<(), String> {
+    let (level_data, level) = load_level(Path::new(&replay.level))?;
+    replay.check_level(&level_data)?;
+    let mut scene = SceneState::new(level, replay.seed);
+    scene.load_polygons(&options.assets)?;
+    replay.play(&mut scene)?;
+    eprintln!("REPLAYED {} ticks, ending in {:?}", replay.inputs.len(), scene.round());
+    Ok(())
+}
+
+pub fn run(options: &Options, replay: Option<Replay>) -> Result<(), String> {
+    let dir = options.assets.as_path();
     let sdl_context = sdl2::init()?;
     let video_subsystem = sdl_context.video()?;
-    let window = video_subsystem.window("rust-sdl2 demo: Cursor", 800, 600)
-      .position_centered()
-      .build()
-      .map_err(|e| e.to_string())?;
+    let mut window_builder = video_subsystem.window("Mouse Game", options.width, options.height);
+    window_builder.position_centered();
+    if options.fullscreen {
+        window_builder.fullscreen_desktop();
+    }
+    let window = window_builder.build().map_err(|e| e.to_string())?;
 
-    let mut canvas = window.into_canvas().software().build().map_err(|e| e.to_string())?;
+    let mut canvas_builder = window.into_canvas();
+    canvas_builder = match options.renderer {
+        Renderer::Software => canvas_builder.software(),
+        Renderer::Accelerated => canvas_builder.accelerated(),
+    };
+    if options.vsync {
+        canvas_builder = canvas_builder.present_vsync();
+    }
+    let mut canvas = canvas_builder.build().map_err(|e| e.to_string())?;
     let mut keys_down = HashMap::<Keycode, ()>::new();
     let mouse_cursor_surface = Surface::load_bmp(dir.join("cursor.bmp"))
         .map_err(|err| format!("failed to load cursor image: {}", err))?;
-    let svg = if let Ok(file_data) = read_to_string(&dir.join("level.svg")) {
-        SVG::from_str(&file_data).unwrap()
-    } else {
-        SVG::new(1024,768)
+    let campaign = match replay {
+        Some(ref replay) => Campaign::new(vec![replay.level.clone()]),
+        None => load_campaign(options)?,
+    };
+    let level_path = Path::new(campaign.current_level()).to_path_buf();
+    let (level_data, level) = load_level(&level_path)?;
+    let seed = match replay {
+        Some(ref replay) => {
+            replay.check_level(&level_data)?;
+            replay.seed
+        },
+        None => new_seed(),
     };
-    let mut scene_state = SceneState::new(canvas.viewport().width(), canvas.viewport().height(), svg);
+    let mut scene = SceneState::new(level, seed);
+    scene.set_actor_collision(match replay {
+        Some(ref replay) => replay.actor_collision,
+        None => options.push,
+    });
+    scene.load_polygons(dir)?;
+    let mut scene_state = GameView::new(canvas.viewport().width(), canvas.viewport().height(), scene, campaign, &load_controls(options)?)?;
+    scene_state.assets = dir.to_path_buf();
+    // controllers plugged in already are announced as added once events are polled
+    scene_state.controller_subsystem = match sdl_context.game_controller() {
+        Ok(subsystem) => Some(subsystem),
+        Err(err) => {
+            eprintln!("no game controllers: {}", err);
+            None
+        },
+    };
+    if options.record.is_some() {
+        let mut recording = Replay::new(&level_path.to_string_lossy(), &level_data, seed);
+        recording.actor_collision = scene_state.scene.actor_collision();
+        scene_state.recording = Some(recording);
+    }
+    if let Some(replay) = replay {
+        scene_state.play_back(replay);
+    } else {
+        scene_state.cat_ai = options.cat_ai.map(CatAi::new);
+        scene_state.mouse_ai = options.mouse_ai.map(MouseAi::new);
+        scene_state.pointer_steering = options.pointer;
+    }
     let cat_path = dir.join("cat.bmp");
     let cat_name = cat_path.to_str().unwrap().to_string();
     let cat_surface = Surface::load_bmp(cat_path)
@@ -120,19 +267,25 @@ pub fn run(dir: &Path) -> Result<(), String> {
     };
     process_dir(&dir.join("stamps"), &mut |p:&fs::DirEntry| {
         let stamp_surface = Surface::load_bmp(p.path()).map_err(
-            |err| io::Error::new(io::ErrorKind::Other, format!("{}: {}", p.path().to_str().unwrap_or("??"), err)))?;
+            |err| io::Error::other(format!("{}: {}", p.path().to_str().unwrap_or("??"), err)))?;
         images.stamps.push(make_texture_surface!(texture_creator, stamp_surface, p.path().to_str().unwrap().to_string()).map_err(
-            |err| io::Error::new(io::ErrorKind::Other, format!("{}: {}", p.path().to_str().unwrap_or("?X?"), err)))?);
+            |err| io::Error::other(format!("{}: {}", p.path().to_str().unwrap_or("?X?"), err)))?);
         Ok(())
     }).map_err(|err| format!("Failed to load stamp {}", err))?;
     for (index, stamp) in images.stamps.iter().enumerate() {
-        images.inventory_map.insert(HrefAndClipMask{url:stamp.name.clone(), clip:String::new()}, index);
+        images.inventory_map.insert(stamp_file_name(&stamp.name).to_string(), index);
     }
+    scene_state.editor.inventory = editor::inventory(&images);
 
     let cursor = Cursor::from_surface(mouse_cursor_surface, 0, 0).map_err(
             |err| format!("failed to load cursor: {}", err))?;
     cursor.set();
-    main::run_main_loop_infinitely(&mut main::MainLoopArg{sdl_context:&sdl_context, scene_state:&mut scene_state, canvas:&mut canvas, images:&mut images, keys_down:&mut keys_down, texture_creator:&texture_creator, main_loop:main_loop})
+    let ret = main::run_main_loop_infinitely(&mut main::MainLoopArg{sdl_context:&sdl_context, scene_state:&mut scene_state, canvas:&mut canvas, images:&mut images, keys_down:&mut keys_down, texture_creator:&texture_creator, main_loop});
+    if let (Some(path), Some(recording)) = (options.record.as_ref(), scene_state.recording.as_ref()) {
+        let mut f = fs::File::create(path).map_err(|err| format!("{}: {}", path.display(), err))?;
+        f.write_all(recording.to_string().as_bytes()).map_err(|err| format!("{}: {}", path.display(), err))?;
+    }
+    ret
 }
 
 
@@ -150,7 +303,19 @@ fn process_dir<F: FnMut(&fs::DirEntry) -> Result<(), io::Error>>(dir: &Path, cb:
 }
 
 fn main() -> Result<(), String> {
-    if let Err(e) = run(Path::new("assets")) {
+    let options = Options::parse(std::env::args().skip(1))?;
+    if options.help {
+        println!("{}", options::USAGE);
+        return Ok(());
+    }
+    let replay = match options.replay {
+        Some(ref path) => Some(load_replay(path)?),
+        None => None,
+    };
+    if options.headless {
+        return replay_headless(&options, replay.as_ref().ok_or("--headless needs --replay")?);
+    }
+    if let Err(e) = run(&options, replay) {
         if e != "Exit" {
             return Err(e);
         }
@@ -158,61 +323,32 @@ fn main() -> Result<(), String> {
     Ok(())
 }
 
-fn main_loop<'a>(sdl_context: &sdl2::Sdl, scene_state: &mut SceneState, canvas: &mut sdl2::render::Canvas<sdl2::video::Window>, images: &mut Images<'a>, keys_down: &mut HashMap<Keycode, ()>, _texture_creator:&'a sdl2::render::TextureCreator<sdl2::video::WindowContext>) -> Result<(), String> {
-    let loop_start_time = time::Instant::now();
+fn main_loop<'a>(sdl_context: &sdl2::Sdl, scene_state: &mut GameView, canvas: &mut sdl2::render::Canvas<sdl2::video::Window>, images: &mut Images<'a>, keys_down: &mut HashMap<Keycode, ()>, _texture_creator:&'a sdl2::render::TextureCreator<sdl2::video::WindowContext>) -> Result<(), String> {
     let mut events = sdl_context.event_pump()?;
-    let mut process_time = std::time::Duration::default();
-    if keys_down.len() != 0 {
-        for event in events.poll_iter() {
-            process(scene_state, images, event, keys_down)?; // always break
-        }
-        scene_state.render(canvas, images)?; // mut images only needed for color mod
-        process_time = loop_start_time.elapsed();
-        if keys_down.len() != 0 {
-            while process_time < scene_state.duration_per_frame {
-                process_time = loop_start_time.elapsed();
-                let mut any_events = false;
-                for event in events.poll_iter() {
-                    process(scene_state, images, event, keys_down)?; // always break
-                    any_events = true;
-                }
-                if any_events {
-
-                    scene_state.render(canvas, images)?;
-                    scene_state.sim()?;
-                }
-            }
-            if scene_state.duration_per_frame > DELTA_DURATION_PER_FRAME + DESIRED_DURATION_PER_FRAME {
-                scene_state.duration_per_frame -= DELTA_DURATION_PER_FRAME;
-            } else {
-                scene_state.duration_per_frame = DESIRED_DURATION_PER_FRAME;
-            }
-            scene_state.apply_keys(&keys_down, None, true);
-            scene_state.render(canvas, images)?;
+    for event in events.poll_iter() {
+        process(scene_state, images, event, keys_down)?;
+    }
+    if scene_state.quit {
+        return Err("Exit".to_string());
+    }
+    // the simulation always advances in whole ticks, however often we get called
+    for _ in 0..scene_state.timestep.advance() {
+        scene_state.sim()?;
+        if let Some(index) = scene_state.level_request.take() {
+            change_level(scene_state, index)?;
         }
-    } else {
-        scene_state.duration_per_frame = START_DURATION_PER_FRAME;
-        if main::IS_EMSCRIPTEN {
-            for event in events.poll_iter() {
-                process(scene_state, images, event, keys_down)?;
-                break;
-            }
-        } else {
-            for event in events.wait_timeout_iter((DESIRED_DURATION_PER_FRAME - process_time).as_millis() as u32) {
-                process(scene_state, images, event, keys_down)?;
-                break;
-            }
+    }
+    scene_state.render(canvas, images)?; // mut images only needed for color mod
+    if !main::IS_EMSCRIPTEN {
+        // emscripten paces us with requestAnimationFrame; natively, sleep until
+        // the next tick is due unless input arrives first
+        let timeout = scene_state.timestep.until_next_tick().as_millis() as u32;
+        if let Some(event) = events.wait_event_timeout(timeout) {
+            process(scene_state, images, event, keys_down)?;
         }
-        scene_state.render(canvas, images)?;
-	scene_state.sim()?;
-    };
+    }
     Ok(())
 }
 
-#[derive(Clone, Debug)]
-struct InventoryItem {
-    stamp_index: usize,
-    stamp_name: String,
-}
 
 
@@ -0,0 +1,199 @@
+// writes out the navigation graph of a level, as seen by one actor, so level
+// designers can spot places it can't get to without playing through them
+extern crate art_stamps;
+extern crate mousegame;
+use art_stamps::SVG;
+use mousegame::{SceneState, Level, NavGraph, NavMove, Actor};
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const USAGE: &str = "usage: navexport <level.svg> [options]
+  --assets <dir>        directory with the stamp outlines (default assets)
+  --actor <cat|mouse>   whose spawn reachability is worked out from (default mouse)
+  --size <pixels>       body size to plan for (default the actor's)
+  --svg <file>          where to write the level with the graph drawn over it
+                        (default <level>.nav.svg)
+  --json <file>         where to write the graph (default <level>.nav.json)
+  --help                print this message";
+
+struct Options {
+    level: PathBuf,
+    assets: PathBuf,
+    actor: Actor,
+    size: Option<f64>,
+    svg: Option<PathBuf>,
+    json: Option<PathBuf>,
+}
+
+impl Options {
+    // None when only the usage was asked for
+    fn parse<I: Iterator<Item=String>>(mut args: I) -> Result<Option<Options>, String> {
+        let mut level = None;
+        let mut options = Options{
+            level:PathBuf::new(),
+            assets:PathBuf::from("assets"),
+            actor:Actor::Mouse,
+            size:None,
+            svg:None,
+            json:None,
+        };
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--assets" => options.assets = PathBuf::from(value(&mut args, &arg)?),
+                "--actor" => options.actor = match value(&mut args, &arg)?.as_str() {
+                    "cat" => Actor::Cat,
+                    "mouse" => Actor::Mouse,
+                    other => return Err(format!("unknown actor {}, expected cat or mouse", other)),
+                },
+                "--size" => {
+                    let size = value(&mut args, &arg)?;
+                    options.size = match size.parse::<f64>() {
+                        Ok(size) if size > 0. => Some(size),
+                        _ => return Err(format!("--size needs a positive number of pixels, not {}", size)),
+                    };
+                },
+                "--svg" => options.svg = Some(PathBuf::from(value(&mut args, &arg)?)),
+                "--json" => options.json = Some(PathBuf::from(value(&mut args, &arg)?)),
+                "--help" | "-h" => return Ok(None),
+                _ if !arg.starts_with("--") && level.is_none() => level = Some(PathBuf::from(arg)),
+                _ => return Err(format!("unknown argument {}\n{}", arg, USAGE)),
+            }
+        }
+        options.level = level.ok_or_else(|| format!("no level given\n{}", USAGE))?;
+        Ok(Some(options))
+    }
+}
+
+fn value<I: Iterator<Item=String>>(args: &mut I, name: &str) -> Result<String, String> {
+    args.next().ok_or_else(|| format!("{} needs a value\n{}", name, USAGE))
+}
+
+// what the export shows: the graph, and how far the actor has to go from its
+// spawn to each node
+struct Export<'a> {
+    nav: &'a NavGraph,
+    size: f64,
+    costs: Vec<f64>,
+}
+
+impl<'a> Export<'a> {
+    fn reachable(&self, node: usize) -> bool {
+        self.costs[node].is_finite()
+    }
+    // the level svg with the graph drawn over it: nodes the actor can get to
+    // in green, the ones it can't in red, and jumps and falls dashed
+    fn svg(&self, level_data: &str) -> Result<String, String> {
+        let end = level_data.rfind("</svg>").ok_or("level is not an svg document")?;
+        let mut overlay = String::new();
+        writeln!(overlay, "<g id=\"nav\" fill=\"none\" stroke-width=\"2\">").unwrap();
+        for (index, node) in self.nav.nodes().iter().enumerate() {
+            if node.clearance < self.size {
+                continue;
+            }
+            for edge in self.nav.edges(index).iter().filter(|edge| edge.clearance >= self.size) {
+                let to = self.nav.nodes()[edge.to].position;
+                writeln!(overlay, "<line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" stroke=\"{}\"{}/>",
+                         node.position.0, node.position.1, to.0, to.1, self.color(index), dashes(edge.kind)).unwrap();
+            }
+        }
+        for (index, node) in self.nav.nodes().iter().enumerate().filter(|&(_, node)| node.clearance >= self.size) {
+            writeln!(overlay, "<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"3\" fill=\"{}\"/>",
+                     node.position.0, node.position.1, self.color(index)).unwrap();
+        }
+        writeln!(overlay, "</g>").unwrap();
+        Ok(format!("{}{}{}", &level_data[..end], overlay, &level_data[end..]))
+    }
+    fn color(&self, node: usize) -> &'static str {
+        if self.reachable(node) {
+            "#00b000"
+        } else {
+            "#e00000"
+        }
+    }
+    // every node and edge, with the clearance each needs so other sizes can
+    // be checked without exporting again; cost is null for nodes the actor
+    // can't get to
+    fn json(&self) -> String {
+        let mut json = String::new();
+        writeln!(json, "{{\n  \"size\": {},\n  \"nodes\": [", self.size).unwrap();
+        let nodes = self.nav.nodes();
+        for (index, node) in nodes.iter().enumerate() {
+            let cost = if self.reachable(index) {
+                format!("{:.1}", self.costs[index])
+            } else {
+                "null".to_string()
+            };
+            writeln!(json, "    {{\"x\": {:.1}, \"y\": {:.1}, \"slope\": {:.3}, \"clearance\": {}, \"cost\": {}}}{}",
+                     node.position.0, node.position.1, node.slope, node.clearance, cost, comma(index, nodes.len())).unwrap();
+        }
+        writeln!(json, "  ],\n  \"edges\": [").unwrap();
+        let edges: Vec<_> = (0..nodes.len()).flat_map(|from| self.nav.edges(from).iter().map(move |edge| (from, edge))).collect();
+        for (index, &(from, edge)) in edges.iter().enumerate() {
+            writeln!(json, "    {{\"from\": {}, \"to\": {}, \"kind\": \"{}\", \"cost\": {:.1}, \"speed\": {:.1}, \"clearance\": {}}}{}",
+                     from, edge.to, kind_name(edge.kind), edge.cost, edge.speed, edge.clearance, comma(index, edges.len())).unwrap();
+        }
+        writeln!(json, "  ]\n}}").unwrap();
+        json
+    }
+}
+
+fn dashes(kind: NavMove) -> &'static str {
+    match kind {
+        NavMove::Walk => "",
+        NavMove::Fall => " stroke-dasharray=\"2 3\"",
+        NavMove::Jump => " stroke-dasharray=\"6 4\"",
+    }
+}
+
+fn kind_name(kind: NavMove) -> &'static str {
+    match kind {
+        NavMove::Walk => "walk",
+        NavMove::Fall => "fall",
+        NavMove::Jump => "jump",
+    }
+}
+
+fn comma(index: usize, len: usize) -> &'static str {
+    if index + 1 < len {
+        ","
+    } else {
+        ""
+    }
+}
+
+// <dir>/<name>.svg becomes <dir>/<name>.nav.<extension>
+fn beside(level: &Path, extension: &str) -> PathBuf {
+    level.with_extension(format!("nav.{}", extension))
+}
+
+fn main() -> Result<(), String> {
+    let options = match Options::parse(std::env::args().skip(1))? {
+        Some(options) => options,
+        None => {
+            println!("{}", USAGE);
+            return Ok(());
+        },
+    };
+    let level_data = fs::read_to_string(&options.level).map_err(|err| format!("{}: {}", options.level.display(), err))?;
+    let svg = SVG::from_str(&level_data).map_err(|err| format!("{}: {:?}", options.level.display(), err))?;
+    let mut scene = SceneState::new(Level::from_svg(svg), 0);
+    scene.load_polygons(&options.assets)?;
+    let body = scene.actor(options.actor).bounds();
+    let size = options.size.unwrap_or(body.max.1 - body.min.1);
+    let spawn = ((body.min.0 + body.max.0) * 0.5, body.max.1);
+    let nav = scene.nav()?;
+    let costs = match nav.nearest(spawn, size) {
+        Some(from) => nav.costs(from, size, f64::INFINITY, |_| 0.),
+        None => vec![f64::INFINITY; nav.nodes().len()],
+    };
+    let export = Export{nav, size, costs};
+    let svg_path = options.svg.clone().unwrap_or_else(|| beside(&options.level, "svg"));
+    let json_path = options.json.clone().unwrap_or_else(|| beside(&options.level, "json"));
+    fs::write(&svg_path, export.svg(&level_data)?).map_err(|err| format!("{}: {}", svg_path.display(), err))?;
+    fs::write(&json_path, export.json()).map_err(|err| format!("{}: {}", json_path.display(), err))?;
+    let standing = (0..nav.nodes().len()).filter(|&node| nav.nodes()[node].clearance >= size).count();
+    let reachable = (0..nav.nodes().len()).filter(|&node| export.reachable(node)).count();
+    eprintln!("NAV {} nodes fit a {} pixel body, {} reachable from the {:?} spawn", standing, size, reachable, options.actor);
+    Ok(())
+}
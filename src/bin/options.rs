@@ -0,0 +1,120 @@
+use std::path::PathBuf;
+use mousegame::Difficulty;
+
+pub const USAGE: &str = "usage: mousegame [options]
+  --assets <dir>        directory with sprites, stamps and levels (default assets)
+  --level <file.svg>    play just this level instead of <assets>/campaign.xml
+  --width <pixels>      window width (default 800)
+  --height <pixels>     window height (default 600)
+  --fullscreen          cover the whole screen
+  --vsync               sync presenting to the display refresh
+  --software            render on the CPU (default)
+  --accelerated         render on the GPU
+  --push                the cat and mouse block and shove each other and can
+                        stand on each other's heads; only running into the
+                        mouse side on catches it
+  --cat-ai <difficulty> the computer plays the cat: easy, normal or hard
+  --mouse-ai <difficulty>
+                        the computer plays the mouse: easy, normal or hard
+  --pointer             the mouse runs towards the pointer; click to jump,
+                        right click to duck (F3 toggles it)
+  --record <file>       write the session's input to a replay file
+  --replay <file>       play back a replay file instead of reading input
+  --headless            with --replay, simulate without opening a window
+  --help                print this message";
+
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum Renderer {
+    Software,
+    Accelerated,
+}
+
+#[derive(Clone,Debug)]
+pub struct Options {
+    pub assets: PathBuf,
+    pub level: Option<PathBuf>,
+    pub width: u32,
+    pub height: u32,
+    pub fullscreen: bool,
+    pub vsync: bool,
+    pub renderer: Renderer,
+    pub push: bool,
+    pub cat_ai: Option<Difficulty>,
+    pub mouse_ai: Option<Difficulty>,
+    pub pointer: bool,
+    pub record: Option<PathBuf>,
+    pub replay: Option<PathBuf>,
+    pub headless: bool,
+    pub help: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options{
+            assets:PathBuf::from("assets"),
+            level:None,
+            width:800,
+            height:600,
+            fullscreen:false,
+            vsync:false,
+            renderer:Renderer::Software,
+            push:false,
+            cat_ai:None,
+            mouse_ai:None,
+            pointer:false,
+            record:None,
+            replay:None,
+            headless:false,
+            help:false,
+        }
+    }
+}
+
+impl Options {
+    pub fn parse<I: Iterator<Item=String>>(mut args: I) -> Result<Options, String> {
+        let mut options = Options::default();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--assets" => options.assets = PathBuf::from(value(&mut args, &arg)?),
+                "--level" => options.level = Some(PathBuf::from(value(&mut args, &arg)?)),
+                "--width" => options.width = pixels(&mut args, &arg)?,
+                "--height" => options.height = pixels(&mut args, &arg)?,
+                "--fullscreen" => options.fullscreen = true,
+                "--vsync" => options.vsync = true,
+                "--software" => options.renderer = Renderer::Software,
+                "--accelerated" => options.renderer = Renderer::Accelerated,
+                "--push" => options.push = true,
+                "--cat-ai" => options.cat_ai = Some(value(&mut args, &arg)?.parse::<Difficulty>()?),
+                "--mouse-ai" => options.mouse_ai = Some(value(&mut args, &arg)?.parse::<Difficulty>()?),
+                "--pointer" => options.pointer = true,
+                "--record" => options.record = Some(PathBuf::from(value(&mut args, &arg)?)),
+                "--replay" => options.replay = Some(PathBuf::from(value(&mut args, &arg)?)),
+                "--headless" => options.headless = true,
+                "--help" | "-h" => options.help = true,
+                _ => return Err(format!("unknown argument {}\n{}", arg, USAGE)),
+            }
+        }
+        if options.headless && options.replay.is_none() {
+            return Err("--headless needs --replay".to_string());
+        }
+        Ok(options)
+    }
+    pub fn level_path(&self) -> PathBuf {
+        match self.level {
+            Some(ref level) => level.clone(),
+            None => self.assets.join("level.svg"),
+        }
+    }
+}
+
+fn value<I: Iterator<Item=String>>(args: &mut I, name: &str) -> Result<String, String> {
+    args.next().ok_or_else(|| format!("{} needs a value\n{}", name, USAGE))
+}
+
+fn pixels<I: Iterator<Item=String>>(args: &mut I, name: &str) -> Result<u32, String> {
+    let arg = value(args, name)?;
+    match arg.parse::<u32>() {
+        Ok(pixels) if pixels > 0 => Ok(pixels),
+        _ => Err(format!("{} needs a positive number of pixels, not {}", name, arg)),
+    }
+}
@@ -0,0 +1,758 @@
+use sdl2::keyboard::Keycode;
+use sdl2::controller::{GameController, Axis, Button};
+use sdl2::mouse::MouseButton;
+use sdl2::GameControllerSubsystem;
+use mousegame::{SceneState, RoundState, Actor, Action, Actions, Input, STICK_MAX, Transform, F64Point, ftransform, itransform, Replay, Campaign, CatAi, MouseAi, Controls, Binding, Command, Edit, stamp_file_name, CAT_SPAWN_STAMP, MOUSE_SPAWN_STAMP};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use sdl2::surface::Surface;
+use sdl2::render::{Texture, BlendMode};
+use sdl2::pixels::Color;
+use sdl2::rect::{Rect, Point};
+use super::main::FixedTimestep;
+use super::editor::{self, Editor, PAN_SPEED};
+
+pub struct TextureSurface<'r> {
+    pub texture: Texture<'r>,
+    pub surface: Surface<'r>,
+    pub name: String,
+}
+pub struct Images<'r> {
+    pub stamps: Vec<TextureSurface<'r>>,
+    // stamp file name to index in stamps
+    pub inventory_map: HashMap<String, usize>,
+    pub mouse: TextureSurface<'r>,
+    pub cat: TextureSurface<'r>,
+}
+
+// what the camera keeps in the middle of the view
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum CameraFocus {
+    Midpoint,
+    Cat,
+    Mouse,
+    // wherever the editor was panned to
+    Editor,
+}
+
+// sticks pushed less than this, out of i16::MAX, count as centered
+const STICK_DEAD_ZONE: u16 = 8000;
+
+// with pointer steering, the mouse runs flat out towards a pointer this many
+// pixels or more to the side of it, slower when closer, and not at all within
+// the dead zone
+const POINTER_FULL_SPEED: f64 = 128.0;
+const POINTER_DEAD_ZONE: f64 = 4.0;
+
+// on-screen touch buttons are this fraction of the window's shorter side
+const TOUCH_BUTTON_FRACTION: u32 = 6;
+
+// modifier keys a binding needs held
+#[derive(Clone,Copy,Debug,Default,PartialEq,Eq)]
+pub struct Modifiers {
+    ctrl: bool,
+    shift: bool,
+}
+
+impl Modifiers {
+    fn count(&self) -> usize {
+        usize::from(self.ctrl) + usize::from(self.shift)
+    }
+    fn held_in(&self, held: Modifiers) -> bool {
+        (!self.ctrl || held.ctrl) && (!self.shift || held.shift)
+    }
+}
+
+// an attached game controller and who it plays
+pub struct Pad {
+    controller: GameController,
+    actor: Actor,
+    // buttons and stick as they are right now
+    actions: Actions,
+}
+
+// the SDL side of the game: turns keys into Input for the scene and draws it
+pub struct GameView{
+    pub scene: SceneState,
+    pub cursor_x: i32,
+    pub cursor_y: i32,
+    pub window_width: u32,
+    pub window_height: u32,
+    pub timestep: FixedTimestep,
+    pub camera_focus: CameraFocus,
+    // two side by side views, the cat's on the left and the mouse's on the right
+    pub split_screen: bool,
+    camera_transform: Transform,
+    // actions held right now, and actions pressed since the last tick so taps
+    // shorter than a tick still reach the simulation
+    held: Input,
+    pressed: Input,
+    // what each key does, with the modifiers it needs
+    bindings: HashMap<Keycode, Vec<(Modifiers, Binding)>>,
+    // opens controllers as they are plugged in; None without controller support
+    pub controller_subsystem: Option<GameControllerSubsystem>,
+    // attached controllers by joystick instance id
+    pads: HashMap<u32, Pad>,
+    // when set, the mouse runs towards the pointer and its buttons jump and
+    // duck, so one hand on the mouse is enough to play
+    pub pointer_steering: bool,
+    pointer_buttons: Actions,
+    // who the on-screen touch buttons play, and the action under each finger
+    // touching the screen, by finger id
+    pub touch_actor: Actor,
+    touches: HashMap<i64, Option<Action>>,
+    // the buttons are only drawn once the screen has been touched
+    touch_seen: bool,
+    // every tick's input is appended here when recording
+    pub recording: Option<Replay>,
+    // when set, input comes from the replay instead of the keyboard
+    playback: Option<(Replay, usize)>,
+    // when set, the computer plays the cat instead of the keyboard
+    pub cat_ai: Option<CatAi>,
+    // and the mouse
+    pub mouse_ai: Option<MouseAi>,
+    pub editor: Editor,
+    pub campaign: Campaign,
+    // where stamp outlines of newly loaded levels come from
+    pub assets: PathBuf,
+    // set when the main loop should load another level of the campaign
+    pub level_request: Option<usize>,
+    pub quit: bool,
+}
+
+impl GameView {
+    pub fn new(width: u32, height:u32, scene: SceneState, campaign: Campaign, controls: &Controls) -> Result<Self, String> {
+        Ok(GameView{
+            scene,
+            cursor_x:0,
+            cursor_y:0,
+            window_width: width,
+            window_height: height,
+            timestep:FixedTimestep::new(mousegame::SIM_TICK_SECONDS),
+            camera_focus:CameraFocus::Midpoint,
+            split_screen:false,
+            camera_transform:Transform::new(0,0),
+            held:Input::default(),
+            pressed:Input::default(),
+            bindings:key_bindings(controls)?,
+            controller_subsystem:None,
+            pads:HashMap::new(),
+            pointer_steering:false,
+            pointer_buttons:Actions::default(),
+            touch_actor:Actor::Mouse,
+            touches:HashMap::new(),
+            touch_seen:false,
+            recording:None,
+            playback:None,
+            cat_ai:None,
+            mouse_ai:None,
+            editor:Editor::new(),
+            campaign,
+            assets:PathBuf::from("assets"),
+            level_request:None,
+            quit:false,
+        })
+    }
+    pub fn play_back(&mut self, replay: Replay) {
+        self.playback = Some((replay, 0));
+    }
+    pub fn sim(&mut self) -> Result<(), String> {
+        if self.editor.active {
+            self.pan();
+            return Ok(());
+        }
+        let input = if let Some((ref replay, ref mut tick)) = self.playback {
+            let input = *replay.inputs.get(*tick).ok_or_else(|| "Exit".to_string())?;
+            *tick += 1;
+            input
+        } else {
+            let mut input = self.held.union(&self.pressed);
+            for pad in self.pads.values() {
+                let actions = input.actions(pad.actor).union(pad.actions);
+                *input.actions_mut(pad.actor) = actions;
+            }
+            if self.pointer_steering {
+                input.mouse = input.mouse.union(self.pointer_actions());
+            }
+            for action in self.touches.values().flatten() {
+                input.actions_mut(self.touch_actor).insert(*action);
+            }
+            if let Some(ref mut cat_ai) = self.cat_ai {
+                input.cat = cat_ai.think(&mut self.scene)?;
+            }
+            if let Some(ref mut mouse_ai) = self.mouse_ai {
+                input.mouse = mouse_ai.think(&mut self.scene)?;
+            }
+            input
+        };
+        self.pressed = Input::default();
+        if let Some(ref mut recording) = self.recording {
+            recording.record(&input);
+        }
+        let previous = self.scene.round();
+        let escaping = matches!(previous, RoundState::MouseEscaped{..});
+        self.scene.sim(&input)?;
+        if std::mem::discriminant(&previous) != std::mem::discriminant(&self.scene.round()) {
+            eprintln!("ROUND {:?}", self.scene.round());
+        }
+        if escaping && !matches!(self.scene.round(), RoundState::MouseEscaped{..}) && !self.level_pinned() {
+            self.level_request = self.campaign.complete_current();
+        }
+        self.camera_transform = self.scene.camera_for(
+            self.focus_point(self.camera_focus, &self.scene.actor(Actor::Cat).location, &self.scene.actor(Actor::Mouse).location),
+            self.window_width, self.window_height);
+        Ok(())
+    }
+    // while editing, the movement keys of either actor move the view around
+    // the level instead
+    fn pan(&mut self) {
+        let input = self.held.union(&self.pressed);
+        self.pressed = Input::default();
+        let (mut dx, mut dy) = (0f64, 0f64);
+        for actions in [input.cat, input.mouse].iter() {
+            dx += f64::from(actions.horizontal());
+            if actions.contains(Action::Jump) {
+                dy -= 1.;
+            }
+            if actions.contains(Action::Down) {
+                dy += 1.;
+            }
+        }
+        let svg = self.scene.svg();
+        let (width, height) = (f64::from(svg.width), f64::from(svg.height));
+        let focus = &mut self.editor.focus;
+        focus.0 = (focus.0 + dx.clamp(-1., 1.) * PAN_SPEED).clamp(0., width);
+        focus.1 = (focus.1 + dy.clamp(-1., 1.) * PAN_SPEED).clamp(0., height);
+        self.camera_transform = self.scene.camera_for(self.editor.focus, self.window_width, self.window_height);
+    }
+    // replays only know about a single level, so the campaign stays put
+    // while one is recorded or played
+    fn level_pinned(&self) -> bool {
+        self.recording.is_some() || self.playback.is_some()
+    }
+    fn select_level(&mut self, index: usize) {
+        if !self.level_pinned() && self.campaign.select(index) {
+            self.level_request = Some(index);
+        }
+    }
+    // the views drawn side by side in full, and what each keeps centered
+    fn views(&self, full: Rect) -> Vec<(Rect, CameraFocus)> {
+        if self.editor.active {
+            vec![(full, CameraFocus::Editor)]
+        } else if self.split_screen {
+            let half = full.width() / 2;
+            vec![(Rect::new(0, 0, half, full.height()), CameraFocus::Cat),
+                 (Rect::new(half as i32, 0, full.width() - half, full.height()), CameraFocus::Mouse)]
+        } else {
+            vec![(full, self.camera_focus)]
+        }
+    }
+    // where the pointer is in the level, seen through the camera of the view
+    // it is over
+    fn pointer_in_level(&self) -> F64Point {
+        let full = Rect::new(0, 0, self.window_width, self.window_height);
+        let views = self.views(full);
+        let &(viewport, focus) = views.iter().find(|(viewport, _)| viewport.contains_point((self.cursor_x, self.cursor_y))).unwrap_or(&views[0]);
+        let camera = self.scene.camera_for(
+            self.focus_point(focus, &self.scene.actor(Actor::Cat).location, &self.scene.actor(Actor::Mouse).location),
+            viewport.width(), viewport.height());
+        itransform(&camera, (f64::from(self.cursor_x - viewport.x()), f64::from(self.cursor_y - viewport.y())))
+    }
+    // the mouse's actions when steered by the pointer
+    fn pointer_actions(&self) -> Actions {
+        let mut actions = self.pointer_buttons;
+        let offset = self.pointer_in_level().0 - self.scene.actor(Actor::Mouse).center().0;
+        if offset.abs() > POINTER_DEAD_ZONE {
+            let push = (offset / POINTER_FULL_SPEED).clamp(-1., 1.);
+            actions.set_stick((push * f64::from(STICK_MAX)) as i8);
+        }
+        actions
+    }
+    pub fn pointer_button(&mut self, button: MouseButton, down: bool) {
+        let action = match button {
+            MouseButton::Left => Action::Jump,
+            MouseButton::Right => Action::Down,
+            _ => return,
+        };
+        if self.editor.active {
+            if down {
+                match action {
+                    Action::Jump => self.click(),
+                    _ => self.command(Command::Delete),
+                }
+            }
+            return;
+        }
+        if down {
+            self.pointer_buttons.insert(action);
+            if self.pointer_steering {
+                self.pressed.mouse.insert(action);
+            }
+        } else {
+            self.pointer_buttons.remove(action);
+        }
+    }
+    // the on-screen d-pad, left, down and right along the bottom left corner,
+    // and the jump button in the bottom right
+    fn touch_buttons(&self) -> [(Rect, Action); 4] {
+        let size = self.window_width.min(self.window_height) / TOUCH_BUTTON_FRACTION;
+        let gap = size / 4;
+        let top = self.window_height as i32 - (size + gap) as i32;
+        let column = |index: i32| gap as i32 + index * (size + gap) as i32;
+        [(Rect::new(column(0), top, size, size), Action::Left),
+         (Rect::new(column(1), top, size, size), Action::Down),
+         (Rect::new(column(2), top, size, size), Action::Right),
+         (Rect::new(self.window_width as i32 - (size + gap) as i32, top, size, size), Action::Jump)]
+    }
+    // a finger went down on or moved over the screen at x, y, as fractions of
+    // its width and height
+    pub fn touch(&mut self, finger: i64, x: f32, y: f32) {
+        self.touch_seen = true;
+        let point = ((x * self.window_width as f32) as i32, (y * self.window_height as f32) as i32);
+        let action = self.touch_buttons().iter().find(|(rect, _)| rect.contains_point(point)).map(|&(_, action)| action);
+        // sliding onto a button presses it, just like tapping it
+        if let Some(action) = action {
+            if self.touches.get(&finger) != Some(&Some(action)) {
+                self.pressed.actions_mut(self.touch_actor).insert(action);
+            }
+        }
+        self.touches.insert(finger, action);
+    }
+    pub fn untouch(&mut self, finger: i64) {
+        self.touches.remove(&finger);
+    }
+    fn focus_point(&self, focus: CameraFocus, cat_location: &Transform, mouse_location: &Transform) -> F64Point {
+        let cat = mousegame::center(cat_location);
+        let mouse = mousegame::center(mouse_location);
+        match focus {
+            CameraFocus::Midpoint => ((cat.0 + mouse.0) * 0.5, (cat.1 + mouse.1) * 0.5),
+            CameraFocus::Cat => cat,
+            CameraFocus::Mouse => mouse,
+            CameraFocus::Editor => self.editor.focus,
+        }
+    }
+    pub fn draw_level<T:sdl2::render::RenderTarget>(&self, canvas: &mut sdl2::render::Canvas<T>, images: &mut Images, camera: &Transform) -> Result<(),String> {
+        for g in self.scene.svg().stamps.iter() {
+            let texture_index = *images.inventory_map.get(stamp_file_name(&g.rect.href.url)).ok_or_else(
+                || format!("no stamp image loaded for {}", g.rect.href.url))?;
+            let img = &mut images.stamps[texture_index];
+            img.texture.set_color_mod(g.rect.fill.r,g.rect.fill.g,g.rect.fill.b);
+            draw_stamp(canvas, &img.texture, &art_stamps::compose(camera, &g.transform))?;
+        }
+        Ok(())
+    }
+    // the spawn and goal markers, and what a click would place under the pointer
+    pub fn draw_editor<T:sdl2::render::RenderTarget>(&self, canvas: &mut sdl2::render::Canvas<T>, images: &mut Images, camera: &Transform) -> Result<(),String> {
+        for marker in self.scene.markers().iter() {
+            draw_outline(canvas, camera, &marker.transform, marker_color(&marker.rect.href.url))?;
+        }
+        let item = match self.editor.selected() {
+            Some(item) => item,
+            None => return Ok(()),
+        };
+        let placement = match self.editor.placement(self.pointer_in_level()) {
+            Some(placement) => placement,
+            None => return Ok(()),
+        };
+        match item.stamp_index {
+            Some(index) => {
+                let fill = self.editor.fill();
+                let img = &mut images.stamps[index];
+                img.texture.set_color_mod(fill.r, fill.g, fill.b);
+                img.texture.set_blend_mode(BlendMode::Blend);
+                img.texture.set_alpha_mod(128);
+                let drawn = draw_stamp(canvas, &img.texture, &art_stamps::compose(camera, &placement));
+                img.texture.set_alpha_mod(255);
+                img.texture.set_blend_mode(BlendMode::None);
+                drawn?;
+            },
+            None => draw_outline(canvas, camera, &placement, marker_color(&item.stamp_name))?,
+        }
+        Ok(())
+    }
+    pub fn draw_actors<T:sdl2::render::RenderTarget>(&self, canvas: &mut sdl2::render::Canvas<T>, images: &mut Images, camera: &Transform, cat_location: &Transform, mouse_location: &Transform) -> Result<(),String> {
+        draw_sprite(canvas, &images.mouse, &art_stamps::compose(camera, mouse_location))?;
+        draw_sprite(canvas, &images.cat, &art_stamps::compose(camera, cat_location))
+    }
+    // a bar along the top of the view showing the round state and the time left in it
+    pub fn draw_status<T:sdl2::render::RenderTarget>(&self, canvas: &mut sdl2::render::Canvas<T>) -> Result<(),String> {
+        let round = self.scene.round();
+        let width = (f64::from(canvas.viewport().width()) * round.remaining()) as u32;
+        canvas.set_draw_color(round_color(round));
+        canvas.fill_rect(Rect::new(0, 0, width, 6))?;
+        // one pip per campaign level: the current one dark, locked ones hollow
+        for index in 0..self.campaign.levels.len() {
+            let pip = Rect::new(4 + 12 * index as i32, 10, 8, 8);
+            if index == self.campaign.current() {
+                canvas.set_draw_color(Color::RGBA(0, 0, 0, 255));
+                canvas.fill_rect(pip)?;
+            } else if index < self.campaign.unlocked() {
+                canvas.set_draw_color(Color::RGBA(128, 128, 128, 255));
+                canvas.fill_rect(pip)?;
+            } else {
+                canvas.set_draw_color(Color::RGBA(128, 128, 128, 255));
+                canvas.draw_rect(pip)?;
+            }
+        }
+        Ok(())
+    }
+    // outlines of the touch buttons, filled in while held
+    pub fn draw_touch_buttons<T:sdl2::render::RenderTarget>(&self, canvas: &mut sdl2::render::Canvas<T>) -> Result<(),String> {
+        canvas.set_draw_color(Color::RGB(96, 96, 96));
+        for &(rect, action) in self.touch_buttons().iter() {
+            if self.touches.values().any(|&touched| touched == Some(action)) {
+                canvas.fill_rect(rect)?;
+            } else {
+                canvas.draw_rect(rect)?;
+            }
+            // an arrow pointing the way the button moves
+            let (cx, cy) = (rect.center().x(), rect.center().y());
+            let r = rect.width() as i32 / 4;
+            let arrow = match action {
+                Action::Left => [Point::new(cx + r, cy - r), Point::new(cx - r, cy), Point::new(cx + r, cy + r)],
+                Action::Right => [Point::new(cx - r, cy - r), Point::new(cx + r, cy), Point::new(cx - r, cy + r)],
+                Action::Down => [Point::new(cx - r, cy - r), Point::new(cx, cy + r), Point::new(cx + r, cy - r)],
+                Action::Jump => [Point::new(cx - r, cy + r), Point::new(cx, cy - r), Point::new(cx + r, cy + r)],
+            };
+            canvas.draw_lines(&arrow[..])?;
+        }
+        Ok(())
+    }
+    // layers are drawn back to front: background, level stamps, actors, the
+    // editor's markers, touch buttons, status
+    pub fn render<T:sdl2::render::RenderTarget>(&self, canvas: &mut sdl2::render::Canvas<T>, images: &mut Images) -> Result<(),String> {
+        let white = Color::RGBA(255, 255, 255, 255);
+        canvas.set_draw_color(white);
+        canvas.clear();
+        let alpha = self.timestep.alpha();
+        let cat_location = self.scene.interpolated(Actor::Cat, alpha);
+        let mouse_location = self.scene.interpolated(Actor::Mouse, alpha);
+        let full = canvas.viewport();
+        for (viewport, focus) in self.views(full) {
+            canvas.set_viewport(viewport);
+            let camera = self.scene.camera_for(self.focus_point(focus, &cat_location, &mouse_location), viewport.width(), viewport.height());
+            self.draw_level(canvas, images, &camera)?; // mut images only needed for color mod
+            self.draw_actors(canvas, images, &camera, &cat_location, &mouse_location)?;
+            if self.editor.active {
+                self.draw_editor(canvas, images, &camera)?;
+            }
+        }
+        canvas.set_viewport(full);
+        if self.touch_seen {
+            self.draw_touch_buttons(canvas)?;
+        }
+        if self.split_screen {
+            let half = (full.width() / 2) as i32;
+            canvas.set_draw_color(Color::RGB(0, 0, 0));
+            canvas.draw_line(Point::new(half, 0), Point::new(half, full.height() as i32))?;
+        }
+        self.draw_status(canvas)?;
+        canvas.present();
+        Ok(())
+    }
+    pub fn apply_keys(&mut self, keys_down: &HashMap<Keycode, ()>, new_key: Option<Keycode>, _repeat:bool) {
+        let modifiers = Modifiers{
+            ctrl:keys_down.contains_key(&Keycode::LCtrl) || keys_down.contains_key(&Keycode::RCtrl),
+            shift:keys_down.contains_key(&Keycode::LShift) || keys_down.contains_key(&Keycode::RShift),
+        };
+        self.held = Input::default();
+        for (key, _) in keys_down.iter() {
+            for binding in self.key_bindings(*key, modifiers) {
+                if let Binding::Play(actor, action) = binding {
+                    self.held.actions_mut(actor).insert(action);
+                }
+            }
+        }
+        let new_bindings = new_key.map(|key| self.key_bindings(key, modifiers)).unwrap_or_default();
+        for binding in new_bindings {
+            match binding {
+                Binding::Play(actor, action) => self.pressed.actions_mut(actor).insert(action),
+                Binding::Command(command) => self.command(command),
+            }
+        }
+    }
+    // what key does with the modifiers held; only the bindings needing the
+    // most of them count, so Ctrl+Shift+Z doesn't also do what Ctrl+Z does,
+    // while a held shift doesn't stop W doing what W does
+    fn key_bindings(&self, key: Keycode, held: Modifiers) -> Vec<Binding> {
+        let bindings: Vec<(Modifiers, Binding)> = self.bindings.get(&key).into_iter().flatten()
+            .filter(|(modifiers, _)| modifiers.held_in(held)).cloned().collect();
+        let most = bindings.iter().map(|(modifiers, _)| modifiers.count()).max().unwrap_or(0);
+        bindings.into_iter().filter(|(modifiers, _)| modifiers.count() == most).map(|(_, binding)| binding).collect()
+    }
+    fn command(&mut self, command: Command) {
+        match command {
+            Command::Quit => self.quit = true,
+            Command::Click => self.click(),
+            Command::SplitScreen => self.split_screen = !self.split_screen,
+            // the edits and their history belong to this level
+            Command::NextLevel | Command::PreviousLevel if self.editor.active => {
+                eprintln!("close the editor before changing level");
+            },
+            Command::NextLevel => {
+                let next = self.campaign.current() + 1;
+                self.select_level(next);
+            },
+            Command::PreviousLevel => {
+                if let Some(previous) = self.campaign.current().checked_sub(1) {
+                    self.select_level(previous);
+                }
+            },
+            Command::PointerSteering => self.pointer_steering = !self.pointer_steering,
+            Command::Editor => self.toggle_editor(),
+            _ if !self.editor.active => {},
+            Command::NextStamp => self.editor.select_next(1),
+            Command::PreviousStamp => self.editor.select_next(-1),
+            Command::Rotate => self.editor.rotate(),
+            Command::ScaleUp => self.editor.resize(1),
+            Command::ScaleDown => self.editor.resize(-1),
+            Command::NextColor => self.editor.next_fill(),
+            Command::Snap => self.editor.snap = !self.editor.snap,
+            Command::Delete => {
+                if let Some((marker, index)) = editor::hit(&self.scene, self.pointer_in_level()) {
+                    self.edit(Edit::Remove{marker, index});
+                }
+            },
+            Command::TurnStamp => {
+                if let Some((false, index)) = editor::hit(&self.scene, self.pointer_in_level()) {
+                    let mut transform = self.scene.svg().stamps[index].transform.clone();
+                    transform.rotate = (transform.rotate + 90.) % 360.;
+                    self.edit(Edit::Transform{index, transform});
+                }
+            },
+            Command::RecolorStamp => {
+                if let Some((false, index)) = editor::hit(&self.scene, self.pointer_in_level()) {
+                    let fill = self.editor.fill();
+                    self.edit(Edit::Recolor{index, fill});
+                }
+            },
+            Command::Undo => {
+                if let Err(err) = self.editor.history.undo(&mut self.scene) {
+                    eprintln!("can't undo: {}", err);
+                }
+            },
+            Command::Redo => {
+                if let Err(err) = self.editor.history.redo(&mut self.scene) {
+                    eprintln!("can't redo: {}", err);
+                }
+            },
+            Command::Save => {
+                if let Err(err) = self.save_level() {
+                    eprintln!("not saved: {}", err);
+                }
+            },
+        }
+    }
+    // editing is kept out of replays, which would no longer match their level
+    fn toggle_editor(&mut self) {
+        if self.editor.active {
+            self.editor.active = false;
+            // the level may have changed under the actors and the computer's routes
+            self.scene.reset_round();
+            if let Some(ref mut cat_ai) = self.cat_ai {
+                cat_ai.reset();
+            }
+            if let Some(ref mut mouse_ai) = self.mouse_ai {
+                mouse_ai.reset();
+            }
+        } else if !self.level_pinned() {
+            self.editor.active = true;
+            self.editor.focus = itransform(&self.camera_transform,
+                (f64::from(self.window_width) * 0.5, f64::from(self.window_height) * 0.5));
+        }
+    }
+    fn edit(&mut self, edit: Edit) {
+        if let Err(err) = self.editor.history.apply(&mut self.scene, edit) {
+            eprintln!("{}", err);
+        }
+    }
+    fn save_level(&self) -> Result<(), String> {
+        let path = self.campaign.current_level();
+        fs::write(path, self.scene.level_svg(&self.assets)?).map_err(|err| format!("{}: {}", path, err))?;
+        eprintln!("SAVED {}", path);
+        Ok(())
+    }
+    // in the editor, places the selected item under the pointer
+    pub fn click(&mut self) {
+        if !self.editor.active {
+            return;
+        }
+        let stamp = match self.editor.stamp_at(self.pointer_in_level(), &self.assets) {
+            Some(stamp) => stamp,
+            None => return,
+        };
+        let marker = mousegame::is_marker(&stamp);
+        let index = if marker {
+            self.scene.markers().len()
+        } else {
+            // outlines come from the assets, not wherever the stamp's url points
+            if let Err(err) = self.scene.load_polygon(&stamp.rect.href.url, &self.assets) {
+                eprintln!("{}", err);
+                return;
+            }
+            self.scene.svg().stamps.len()
+        };
+        self.edit(Edit::Insert{marker, index, stamp});
+    }
+    // opens a newly plugged in controller, giving it to whichever actor has
+    // fewer controllers so far, the cat first
+    pub fn add_controller(&mut self, joystick_index: u32) -> Result<(), String> {
+        let controller = match self.controller_subsystem {
+            Some(ref subsystem) => subsystem.open(joystick_index).map_err(|err| err.to_string())?,
+            None => return Ok(()),
+        };
+        let cats = self.pads.values().filter(|pad| pad.actor == Actor::Cat).count();
+        let actor = if cats * 2 <= self.pads.len() { Actor::Cat } else { Actor::Mouse };
+        eprintln!("CONTROLLER {} plays the {:?}", controller.name(), actor);
+        self.pads.insert(controller.instance_id(), Pad{controller, actor, actions:Actions::default()});
+        Ok(())
+    }
+    pub fn remove_controller(&mut self, instance_id: u32) {
+        self.pads.remove(&instance_id);
+    }
+    pub fn controller_button(&mut self, instance_id: u32, button: Button, down: bool) {
+        let pad = match self.pads.get_mut(&instance_id) {
+            Some(pad) => pad,
+            None => return,
+        };
+        // back hands the controller over to the other actor
+        if button == Button::Back {
+            if down {
+                pad.actor = match pad.actor {
+                    Actor::Cat => Actor::Mouse,
+                    Actor::Mouse => Actor::Cat,
+                };
+                pad.actions = Actions::default();
+                eprintln!("CONTROLLER {} plays the {:?}", pad.controller.name(), pad.actor);
+            }
+            return;
+        }
+        let action = match button {
+            Button::DPadLeft => Action::Left,
+            Button::DPadRight => Action::Right,
+            Button::A | Button::B => Action::Jump,
+            Button::DPadDown => Action::Down,
+            _ => return,
+        };
+        if down {
+            pad.actions.insert(action);
+            self.pressed.actions_mut(pad.actor).insert(action);
+        } else {
+            pad.actions.remove(action);
+        }
+    }
+    // the left stick steers, and pushed down it counts as holding down
+    pub fn controller_axis(&mut self, instance_id: u32, axis: Axis, value: i16) {
+        let pad = match self.pads.get_mut(&instance_id) {
+            Some(pad) => pad,
+            None => return,
+        };
+        let pushed = value.unsigned_abs() >= STICK_DEAD_ZONE;
+        match axis {
+            Axis::LeftX => {
+                let stick = if pushed {
+                    (i32::from(value) * i32::from(STICK_MAX) / i32::from(i16::MAX)) as i8
+                } else {
+                    0
+                };
+                pad.actions.set_stick(stick);
+            },
+            Axis::LeftY => {
+                if pushed && value > 0 {
+                    pad.actions.insert(Action::Down);
+                } else {
+                    pad.actions.remove(Action::Down);
+                }
+            },
+            _ => {},
+        }
+    }
+}
+
+// looks up the SDL key behind each key name, after its modifiers
+fn key_bindings(controls: &Controls) -> Result<HashMap<Keycode, Vec<(Modifiers, Binding)>>, String> {
+    let mut bindings = HashMap::<Keycode, Vec<(Modifiers, Binding)>>::new();
+    for &(ref name, binding) in controls.keys.iter() {
+        let mut modifiers = Modifiers::default();
+        let mut key_name = name.as_str();
+        loop {
+            if let Some(rest) = key_name.strip_prefix("Ctrl+") {
+                modifiers.ctrl = true;
+                key_name = rest;
+            } else if let Some(rest) = key_name.strip_prefix("Shift+") {
+                modifiers.shift = true;
+                key_name = rest;
+            } else {
+                break;
+            }
+        }
+        let key = Keycode::from_name(key_name).ok_or_else(|| format!("unknown key {}", name))?;
+        bindings.entry(key).or_default().push((modifiers, binding));
+    }
+    Ok(bindings)
+}
+
+fn round_color(round: RoundState) -> Color {
+    match round {
+        RoundState::Countdown{..} => Color::RGB(255, 200, 0),
+        RoundState::Playing{..} => Color::RGB(0, 192, 0),
+        RoundState::Caught{..} => Color::RGB(220, 0, 0),
+        RoundState::MouseEscaped{..} => Color::RGB(0, 96, 255),
+        RoundState::Timeout{..} => Color::RGB(128, 128, 128),
+    }
+}
+
+// sdl turns a copy about a point of the rect it is drawn into, while a stamp
+// is scaled about its middle, so the rect is moved to keep the middle in place
+fn draw_stamp<T:sdl2::render::RenderTarget>(canvas: &mut sdl2::render::Canvas<T>, texture: &Texture, location: &Transform) -> Result<(),String> {
+    let width = location.midx * 2. * location.scale;
+    let height = location.midy * 2. * location.scale;
+    canvas.copy_ex(
+        texture,
+        None,
+        Some(Rect::new((location.tx + location.midx * (1. - location.scale)) as i32,
+                       (location.ty + location.midy * (1. - location.scale)) as i32,
+                       width as u32, height as u32)),
+        location.rotate,
+        Point::new((width * 0.5) as i32, (height * 0.5) as i32),
+        false,
+        false,
+    ).map_err(|err| format!("{:?}", err))
+}
+
+// the box a marker covers, in its own color
+fn draw_outline<T:sdl2::render::RenderTarget>(canvas: &mut sdl2::render::Canvas<T>, camera: &Transform, location: &Transform, color: Color) -> Result<(),String> {
+    let corners = location.to_bbox();
+    let mut points: Vec<Point> = corners.iter().map(|&corner| {
+        let (x, y) = ftransform(camera, corner);
+        Point::new(x as i32, y as i32)
+    }).collect();
+    points.push(points[0]);
+    canvas.set_draw_color(color);
+    canvas.draw_lines(&points[..])
+}
+
+fn marker_color(url: &str) -> Color {
+    let name = stamp_file_name(url);
+    if name.starts_with(CAT_SPAWN_STAMP) {
+        Color::RGB(220, 0, 0)
+    } else if name.starts_with(MOUSE_SPAWN_STAMP) {
+        Color::RGB(0, 96, 255)
+    } else {
+        Color::RGB(0, 192, 0)
+    }
+}
+
+fn draw_sprite<T:sdl2::render::RenderTarget>(canvas: &mut sdl2::render::Canvas<T>, sprite: &TextureSurface, location: &Transform) -> Result<(),String> {
+    canvas.copy_ex(
+        &sprite.texture,
+        None,
+        Some(Rect::new(location.tx as i32, location.ty as i32,
+                       sprite.surface.width(), sprite.surface.height())),
+        0.0,
+        Point::new(0,0),//centre
+        false,// flip horiz
+        false,// flip vert
+    ).map_err(|err| format!("{:?}", err))
+}
@@ -0,0 +1,109 @@
+// an ordered list of levels, of which the first few are unlocked, e.g.
+// <campaign>
+//   <level href="level.svg"/>
+//   <level href="attic.svg"/>
+// </campaign>
+
+#[derive(Debug, Deserialize)]
+struct CampaignXml {
+    #[serde(rename="level", default)]
+    levels: Vec<LevelXml>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LevelXml {
+    href: String,
+}
+
+#[derive(Clone, Debug)]
+pub struct Campaign {
+    // level files, as written in the manifest
+    pub levels: Vec<String>,
+    current: usize,
+    // how many levels, from the start, can be played
+    unlocked: usize,
+}
+
+impl Campaign {
+    pub fn new(levels: Vec<String>) -> Self {
+        Campaign{
+            levels,
+            current:0,
+            unlocked:1,
+        }
+    }
+    pub fn current(&self) -> usize {
+        self.current
+    }
+    pub fn current_level(&self) -> &str {
+        &self.levels[self.current]
+    }
+    pub fn unlocked(&self) -> usize {
+        self.unlocked
+    }
+    // unlocks the level after the current one and moves on to it;
+    // returns its index, or None when the campaign is over
+    pub fn complete_current(&mut self) -> Option<usize> {
+        let next = self.current + 1;
+        if next >= self.levels.len() {
+            return None;
+        }
+        self.unlocked = self.unlocked.max(next + 1);
+        self.current = next;
+        Some(next)
+    }
+    // switches to an unlocked level, returning false if it is still locked
+    pub fn select(&mut self, index: usize) -> bool {
+        if index >= self.unlocked.min(self.levels.len()) {
+            return false;
+        }
+        self.current = index;
+        true
+    }
+}
+
+impl std::str::FromStr for Campaign {
+    type Err = String;
+    fn from_str(manifest: &str) -> Result<Self, String> {
+        let xml: CampaignXml = serde_xml_rs::from_str(manifest).map_err(|err| format!("bad campaign: {:?}", err))?;
+        if xml.levels.is_empty() {
+            return Err("campaign has no levels".to_string());
+        }
+        Ok(Campaign::new(xml.levels.into_iter().map(|level| level.href).collect()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_the_shipped_manifest() {
+        let campaign = std::fs::read_to_string("assets/campaign.xml").unwrap().parse::<Campaign>().unwrap();
+        assert_eq!(campaign.levels, vec!["level.svg".to_string(), "attic.svg".to_string()]);
+        assert_eq!(campaign.current(), 0);
+        assert_eq!(campaign.unlocked(), 1);
+    }
+
+    #[test]
+    fn rejects_a_manifest_without_levels() {
+        assert!("<campaign></campaign>".parse::<Campaign>().is_err());
+        assert!("not xml".parse::<Campaign>().is_err());
+    }
+
+    #[test]
+    fn completing_a_level_unlocks_the_next() {
+        let mut campaign = Campaign::new(vec!["a".to_string(), "b".to_string(), "c".to_string()]);
+        assert!(!campaign.select(1));
+        assert_eq!(campaign.complete_current(), Some(1));
+        assert_eq!(campaign.current_level(), "b");
+        assert_eq!(campaign.unlocked(), 2);
+        // going back doesn't lock anything again
+        assert!(campaign.select(0));
+        assert!(campaign.select(1));
+        assert!(!campaign.select(2));
+        assert_eq!(campaign.complete_current(), Some(2));
+        assert_eq!(campaign.complete_current(), None);
+        assert_eq!(campaign.current_level(), "c");
+    }
+}
@@ -0,0 +1,550 @@
+// collision of the actors' boxes against the outlines of the level's stamps
+use art_stamps::{SVG, F64Point, ftransform, g};
+use std::collections::HashMap;
+
+// distance between the points tested along each side of a body; less than the
+// width of the thinnest stamp, so nothing slips between them
+const SAMPLE_SPACING: f64 = 4.0;
+// ledges up to this high are stepped onto rather than walked into, since
+// neighbouring stamps rarely line up to the pixel
+const STEP_HEIGHT: f64 = 4.0;
+// the furthest a body may move into a surface in one tick; under half the
+// width of the thinnest stamp, so pushing it back out always returns it to
+// the side it came from
+const MAX_PENETRATION: f64 = 2.0;
+// how far past a surface a body is pushed, so it ends up just outside it
+const SKIN: f64 = 0.001;
+// how many surfaces a single move may slide off before it just stops
+const SLIDE_PASSES: usize = 3;
+// surfaces whose normal points at least this far up are ground that can be
+// stood on and jumped from, i.e. slopes up to 60 degrees; the same angle
+// downwards makes a ceiling
+pub const GROUND_NORMAL_Y: f32 = -0.5;
+
+// which way a surface touching a body faces
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum ContactSide {
+    Ground,
+    Ceiling,
+    // the surface is on the body's left and pushes it right
+    WallLeft,
+    WallRight,
+}
+
+impl ContactSide {
+    // normal points out of the surface
+    pub fn of(normal: (f32, f32)) -> ContactSide {
+        if normal.1 <= GROUND_NORMAL_Y {
+            ContactSide::Ground
+        } else if normal.1 >= -GROUND_NORMAL_Y {
+            ContactSide::Ceiling
+        } else if normal.0 > 0. {
+            ContactSide::WallLeft
+        } else {
+            ContactSide::WallRight
+        }
+    }
+}
+
+// the surfaces a body touched during the latest tick
+#[derive(Clone,Copy,Debug,Default,PartialEq,Eq)]
+pub struct Contacts {
+    pub grounded: bool,
+    pub ceiling: bool,
+    pub wall_left: bool,
+    pub wall_right: bool,
+}
+
+impl Contacts {
+    pub fn add(&mut self, side: ContactSide) {
+        match side {
+            ContactSide::Ground => self.grounded = true,
+            ContactSide::Ceiling => self.ceiling = true,
+            ContactSide::WallLeft => self.wall_left = true,
+            ContactSide::WallRight => self.wall_right = true,
+        }
+    }
+}
+
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub struct Aabb {
+    pub min: F64Point,
+    pub max: F64Point,
+}
+
+impl Aabb {
+    pub fn around(points: &[F64Point]) -> Aabb {
+        let mut ret = Aabb{min:(f64::MAX, f64::MAX), max:(f64::MIN, f64::MIN)};
+        for point in points.iter() {
+            ret.min = (ret.min.0.min(point.0), ret.min.1.min(point.1));
+            ret.max = (ret.max.0.max(point.0), ret.max.1.max(point.1));
+        }
+        ret
+    }
+    pub fn overlaps(&self, other: &Aabb) -> bool {
+        self.min.0 < other.max.0 && other.min.0 < self.max.0 && self.min.1 < other.max.1 && other.min.1 < self.max.1
+    }
+    pub fn expanded(&self, margin: f64) -> Aabb {
+        Aabb{
+            min:(self.min.0 - margin, self.min.1 - margin),
+            max:(self.max.0 + margin, self.max.1 + margin),
+        }
+    }
+    pub fn moved(&self, offset: F64Point) -> Aabb {
+        Aabb{min:add(self.min, offset), max:add(self.max, offset)}
+    }
+    pub fn contains(&self, point: F64Point) -> bool {
+        point.0 > self.min.0 && point.0 < self.max.0 && point.1 > self.min.1 && point.1 < self.max.1
+    }
+    // points spaced at most SAMPLE_SPACING apart all around the edge, each
+    // with the direction its side faces
+    fn perimeter(&self) -> Vec<(F64Point, F64Point)> {
+        let width = self.max.0 - self.min.0;
+        let height = self.max.1 - self.min.1;
+        let across = (width / SAMPLE_SPACING).ceil().max(1.) as usize;
+        let down = (height / SAMPLE_SPACING).ceil().max(1.) as usize;
+        let mut ret = Vec::with_capacity(2 * (across + down));
+        for index in 0..across {
+            let x = self.min.0 + width * index as f64 / across as f64;
+            ret.push(((x, self.min.1), (0., -1.)));
+            ret.push(((x + width / across as f64, self.max.1), (0., 1.)));
+        }
+        for index in 0..down {
+            let y = self.min.1 + height * index as f64 / down as f64;
+            ret.push(((self.max.0, y), (1., 0.)));
+            ret.push(((self.min.0, y + height / down as f64), (-1., 0.)));
+        }
+        ret
+    }
+}
+
+// the outline of one stamp, placed in the level
+#[derive(Clone,Debug)]
+pub struct Shape {
+    pub outline: Vec<F64Point>,
+    pub bounds: Aabb,
+}
+
+impl Shape {
+    pub fn new(outline: Vec<F64Point>) -> Self {
+        Shape{
+            bounds:Aabb::around(&outline),
+            outline,
+        }
+    }
+    // even-odd rule, so outlines made of several joined polygons work too
+    pub fn contains(&self, point: F64Point) -> bool {
+        if !self.bounds.contains(point) {
+            return false;
+        }
+        let mut inside = false;
+        let mut last = self.outline[self.outline.len() - 1];
+        for &cur in self.outline.iter() {
+            if (cur.1 > point.1) != (last.1 > point.1)
+                && point.0 < last.0 + (cur.0 - last.0) * (point.1 - last.1) / (cur.1 - last.1) {
+                inside = !inside;
+            }
+            last = cur;
+        }
+        inside
+    }
+}
+
+// the outline of stamp placed in the level, loading it into cache if it isn't
+// there yet; None for stamps too small to have an inside
+pub fn stamp_shape(svg: &SVG, stamp: &g, cache: &mut HashMap<String,Vec<F64Point>>) -> Result<Option<Shape>, String> {
+    let url = &stamp.rect.href.url;
+    if !cache.contains_key(url) {
+        let polygon = svg.load_polygon(url).map_err(|err| format!("{}: {:?}", url, err))?;
+        cache.insert(url.clone(), polygon);
+    }
+    let polygon = &cache[url];
+    if polygon.len() < 3 {
+        return Ok(None);
+    }
+    Ok(Some(Shape::new(polygon.iter().map(|point| ftransform(&stamp.transform, *point)).collect())))
+}
+
+// the largest push needed to move body out of any one shape it overlaps, or
+// None when it is clear; the push points away from the surface, so it also
+// gives the surface normal
+pub fn deepest_push(shapes: &[&Shape], body: &Aabb) -> Option<F64Point> {
+    let samples = body.perimeter();
+    let mut deepest: Option<F64Point> = None;
+    let mut consider = |push: F64Point| {
+        if deepest.is_none_or(|best| length(push) > length(best)) {
+            deepest = Some(push);
+        }
+    };
+    for shape in shapes.iter().filter(|shape| shape.bounds.overlaps(body)) {
+        // the body's edge inside the shape
+        for &(point, facing) in samples.iter() {
+            if shape.contains(point) {
+                if let Some(push) = point_push(shapes, shape, point, facing) {
+                    consider(push);
+                }
+            }
+        }
+        // corners of the shape poking into the body
+        for &vertex in shape.outline.iter() {
+            if body.contains(vertex) {
+                consider(vertex_push(shapes, shape, body, vertex));
+            }
+        }
+    }
+    deepest
+}
+
+// motion shortened so that body ends no more than MAX_PENETRATION past the
+// first surface in its way, so fast bodies can't pass through thin stamps;
+// only the part of the motion going into that surface is stopped and the
+// rest slides along it, so the pull of gravity doesn't slow walking
+pub fn clip_motion(shapes: &[&Shape], body: &Aabb, motion: F64Point) -> F64Point {
+    let mut moved = (0., 0.);
+    let mut rest = motion;
+    // the motion into the surfaces hit, which only goes as far as
+    // MAX_PENETRATION once the sliding is done; sliding from inside a
+    // surface would miss it and stop at its far side instead
+    let mut sink = (0., 0.);
+    for pass in 0..SLIDE_PASSES {
+        let distance = length(rest);
+        if distance <= MAX_PENETRATION {
+            break;
+        }
+        let (t, surface) = match time_of_impact(shapes, &body.moved(moved), rest) {
+            Some(hit) if distance * hit.0 + MAX_PENETRATION < distance => hit,
+            _ => break,
+        };
+        // up to just short of the surface
+        let touch = (t - SKIN / distance).max(0.);
+        moved = add(moved, scale(rest, touch));
+        rest = scale(rest, 1. - touch);
+        let normal = (-surface.1, surface.0);
+        let into = scale(normal, dot(rest, normal) / dot(normal, normal));
+        sink = add(sink, into);
+        rest = if pass + 1 == SLIDE_PASSES {(0., 0.)} else {sub(rest, into)};
+    }
+    let sunk = length(sink);
+    if sunk > MAX_PENETRATION {
+        sink = scale(sink, MAX_PENETRATION / sunk);
+    }
+    add(add(moved, rest), sink)
+}
+
+// the fraction of motion after which body first touches a shape, if it does,
+// along with the direction of the edge it touches; a box meets a polygon
+// either with one of its corners on an edge of the polygon or with one of the
+// polygon's vertices on an edge of the box
+pub fn time_of_impact(shapes: &[&Shape], body: &Aabb, motion: F64Point) -> Option<(f64, F64Point)> {
+    let swept = Aabb{
+        min:(body.min.0.min(body.min.0 + motion.0), body.min.1.min(body.min.1 + motion.1)),
+        max:(body.max.0.max(body.max.0 + motion.0), body.max.1.max(body.max.1 + motion.1)),
+    };
+    let corners = [body.min, (body.max.0, body.min.1), body.max, (body.min.0, body.max.1)];
+    let backwards = scale(motion, -1.);
+    let mut first: Option<(f64, F64Point)> = None;
+    let mut consider = |t: Option<f64>, a: F64Point, b: F64Point| {
+        if let Some(t) = t {
+            if first.is_none_or(|first| t < first.0) {
+                first = Some((t, sub(b, a)));
+            }
+        }
+    };
+    for shape in shapes.iter().filter(|shape| shape.bounds.overlaps(&swept)) {
+        let mut last = shape.outline[shape.outline.len() - 1];
+        for &cur in shape.outline.iter() {
+            for &corner in corners.iter() {
+                consider(ray_vs_segment(corner, motion, last, cur), last, cur);
+            }
+            last = cur;
+        }
+        for &vertex in shape.outline.iter() {
+            for index in 0..corners.len() {
+                let next = corners[(index + 1) % corners.len()];
+                consider(ray_vs_segment(vertex, backwards, corners[index], next), corners[index], next);
+            }
+        }
+    }
+    first
+}
+
+// whether the segment from a to b stays out of every shape
+pub fn segment_clear(shapes: &[&Shape], a: F64Point, b: F64Point) -> bool {
+    let dir = sub(b, a);
+    let bounds = Aabb::around(&[a, b]).expanded(SKIN);
+    for shape in shapes.iter().filter(|shape| shape.bounds.overlaps(&bounds)) {
+        if shape.contains(a) {
+            return false;
+        }
+        let mut last = shape.outline[shape.outline.len() - 1];
+        for &cur in shape.outline.iter() {
+            if ray_vs_segment(a, dir, last, cur).is_some() {
+                return false;
+            }
+            last = cur;
+        }
+    }
+    true
+}
+
+// whether body overlaps no shape at all
+pub fn box_clear(shapes: &[&Shape], body: &Aabb) -> bool {
+    let corners = [body.min, (body.max.0, body.min.1), body.max, (body.min.0, body.max.1)];
+    for shape in shapes.iter().filter(|shape| shape.bounds.overlaps(body)) {
+        if corners.iter().any(|&corner| shape.contains(corner)) || shape.outline.iter().any(|&vertex| body.contains(vertex)) {
+            return false;
+        }
+        // an edge passing right through the box, corner to corner
+        let mut last = shape.outline[shape.outline.len() - 1];
+        for &cur in shape.outline.iter() {
+            for index in 0..corners.len() {
+                let next = corners[(index + 1) % corners.len()];
+                if ray_vs_segment(corners[index], sub(next, corners[index]), last, cur).is_some() {
+                    return false;
+                }
+            }
+            last = cur;
+        }
+    }
+    true
+}
+
+// how far along dir, as a fraction of it, the ray from origin crosses the segment a-b
+fn ray_vs_segment(origin: F64Point, dir: F64Point, a: F64Point, b: F64Point) -> Option<f64> {
+    let edge = sub(b, a);
+    let denominator = cross(dir, edge);
+    if denominator == 0. {
+        return None;
+    }
+    let to_a = sub(a, origin);
+    let t = cross(to_a, edge) / denominator;
+    let s = cross(to_a, dir) / denominator;
+    if (0. ..=1.).contains(&t) && (0. ..=1.).contains(&s) {
+        Some(t)
+    } else {
+        None
+    }
+}
+
+// the shortest way out of shape for a point on the side of a body facing
+// the given way, preferring edges that don't lead straight into a neighbouring
+// shape, so bodies glide over the seams between stamps instead of snagging on
+// them; pushing the point out through its own side would only drag the rest
+// of the body further in, so those ways out are skipped
+fn point_push(shapes: &[&Shape], shape: &Shape, point: F64Point, facing: F64Point) -> Option<F64Point> {
+    let mut exits = Vec::with_capacity(shape.outline.len());
+    let mut last = shape.outline[shape.outline.len() - 1];
+    for &cur in shape.outline.iter() {
+        let exit = closest_on_segment(point, last, cur);
+        let distance = length(sub(exit, point));
+        if distance > 0. && dot(sub(exit, point), facing) <= 0. {
+            exits.push((distance, exit));
+        }
+        last = cur;
+    }
+    exits.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+    let push_through = |&(distance, exit): &(f64, F64Point)| {
+        let direction = scale(sub(exit, point), 1. / distance);
+        add(sub(exit, point), scale(direction, SKIN))
+    };
+    for candidate in exits.iter() {
+        let push = push_through(candidate);
+        if !shapes.iter().any(|other| other.contains(add(point, push))) {
+            return Some(push);
+        }
+    }
+    exits.first().map(push_through)
+}
+
+// the shortest move along an axis that puts vertex outside body, again
+// avoiding moves that would leave body overlapping a neighbouring shape
+fn vertex_push(shapes: &[&Shape], shape: &Shape, body: &Aabb, vertex: F64Point) -> F64Point {
+    let mut pushes = [
+        (vertex.0 - body.min.0 + SKIN, 0.),
+        (vertex.0 - body.max.0 - SKIN, 0.),
+        (0., vertex.1 - body.min.1 + SKIN),
+        (0., vertex.1 - body.max.1 - SKIN),
+    ];
+    pushes.sort_by(|a, b| length(*a).partial_cmp(&length(*b)).unwrap_or(std::cmp::Ordering::Equal));
+    let step_up = pushes.iter().position(|push| push.1 < 0. && -push.1 <= STEP_HEIGHT);
+    if let Some(step_up) = step_up {
+        pushes[..=step_up].rotate_right(1);
+    }
+    for &push in pushes.iter() {
+        // afterwards the body lies right beside the vertex, in the direction
+        // it was pushed; check no other shape is there too
+        let direction = scale(push, 1. / length(push));
+        let beside = add(vertex, scale(direction, SKIN * 10.));
+        let across = (direction.1 * SKIN * 10., direction.0 * SKIN * 10.);
+        let blocked = |other: &&Shape| !std::ptr::eq(*other, shape) && (other.contains(add(beside, across)) || other.contains(sub(beside, across)));
+        if !shapes.iter().any(blocked) {
+            return push;
+        }
+    }
+    pushes[0]
+}
+
+fn closest_on_segment(point: F64Point, a: F64Point, b: F64Point) -> F64Point {
+    let edge = sub(b, a);
+    let edge_length_sq = edge.0 * edge.0 + edge.1 * edge.1;
+    if edge_length_sq == 0. {
+        return a;
+    }
+    let t = ((point.0 - a.0) * edge.0 + (point.1 - a.1) * edge.1) / edge_length_sq;
+    add(a, scale(edge, t.clamp(0., 1.)))
+}
+
+fn add(a: F64Point, b: F64Point) -> F64Point {
+    (a.0 + b.0, a.1 + b.1)
+}
+
+fn sub(a: F64Point, b: F64Point) -> F64Point {
+    (a.0 - b.0, a.1 - b.1)
+}
+
+fn scale(a: F64Point, factor: f64) -> F64Point {
+    (a.0 * factor, a.1 * factor)
+}
+
+fn cross(a: F64Point, b: F64Point) -> f64 {
+    a.0 * b.1 - a.1 * b.0
+}
+
+fn dot(a: F64Point, b: F64Point) -> f64 {
+    a.0 * b.0 + a.1 * b.1
+}
+
+fn length(a: F64Point) -> f64 {
+    (a.0 * a.0 + a.1 * a.1).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::level::fixtures;
+
+    fn rect(min: F64Point, max: F64Point) -> Shape {
+        Shape::new(vec![min, (max.0, min.1), max, (min.0, max.1)])
+    }
+
+    fn near(a: F64Point, b: F64Point) -> bool {
+        length(sub(a, b)) < 0.01
+    }
+
+    #[test]
+    fn stamps_are_placed_by_their_transform() {
+        let level = fixtures::level(&fixtures::level_data(512, 512, &fixtures::stamp("square", 100., 200., 64)));
+        let shape = stamp_shape(&level.svg, &level.svg.stamps[0], &mut HashMap::new()).unwrap().unwrap();
+        // the outline is traced from the image, so it sits within a pixel of its edges
+        let within_a_pixel = |a: F64Point, b: F64Point| (a.0 - b.0).abs() <= 1. && (a.1 - b.1).abs() <= 1.;
+        assert!(within_a_pixel(shape.bounds.min, (100., 200.)) && within_a_pixel(shape.bounds.max, (164., 264.)), "{:?}", shape.bounds);
+        assert!(shape.contains((132., 232.)));
+        assert!(!shape.contains((90., 232.)));
+    }
+
+    #[test]
+    fn outlines_with_holes_are_inside_by_even_odd() {
+        // a square with a square hole, joined into one outline
+        let shape = Shape::new(vec![(0., 0.), (100., 0.), (100., 100.), (0., 100.), (0., 0.),
+                                    (25., 25.), (25., 75.), (75., 75.), (75., 25.), (25., 25.)]);
+        assert!(shape.contains((10., 50.)));
+        assert!(!shape.contains((50., 50.)));
+        assert!(!shape.contains((150., 50.)));
+    }
+
+    #[test]
+    fn a_box_rests_on_flat_ground() {
+        let floor = rect((0., 100.), (200., 200.));
+        let resting = Aabb{min:(50., 68.), max:(82., 100. - SKIN)};
+        assert_eq!(deepest_push(&[&floor], &resting), None);
+        let sunk = resting.moved((0., 1.5));
+        let push = deepest_push(&[&floor], &sunk).unwrap();
+        assert!(near(push, (0., -1.5 - SKIN)), "{:?}", push);
+    }
+
+    #[test]
+    fn a_box_is_pushed_out_of_a_slope_along_its_normal() {
+        let ramp = Shape::new(vec![(0., 200.), (200., 0.), (200., 200.)]);
+        let body = Aabb{min:(80., 80.), max:(110., 110.)};
+        let push = deepest_push(&[&ramp], &body).unwrap();
+        // up and away from the slope, at 45 degrees
+        assert!(push.0 < 0. && (push.0 - push.1).abs() < 0.01, "{:?}", push);
+        assert!(ContactSide::of(((push.0 / length(push)) as f32, (push.1 / length(push)) as f32)) == ContactSide::Ground);
+        assert!(box_clear(&[&ramp], &body.moved(push)));
+    }
+
+    #[test]
+    fn a_ledge_poking_into_the_side_pushes_sideways() {
+        let ledge = rect((0., 100.), (100., 110.));
+        let body = Aabb{min:(95., 90.), max:(130., 125.)};
+        let push = deepest_push(&[&ledge], &body).unwrap();
+        assert!(near(push, (5. + SKIN, 0.)), "{:?}", push);
+    }
+
+    #[test]
+    fn a_body_in_a_notch_stays_in_it() {
+        let cup = Shape::new(vec![(0., 0.), (20., 0.), (20., 80.), (80., 80.), (80., 0.), (100., 0.), (100., 100.), (0., 100.)]);
+        // pressed into the right wall of the notch, which is thinner than
+        // the way out past it
+        let body = Aabb{min:(40., 40.), max:(82., 79.)};
+        let push = deepest_push(&[&cup], &body).unwrap();
+        assert!(near(push, (-2. - SKIN, 0.)), "{:?}", push);
+        // and sunk into its floor
+        let body = Aabb{min:(30., 42.), max:(70., 82.)};
+        let push = deepest_push(&[&cup], &body).unwrap();
+        assert!(near(push, (0., -2. - SKIN)), "{:?}", push);
+    }
+
+    #[test]
+    fn small_moves_are_never_clipped() {
+        let wall = rect((100., 0.), (104., 200.));
+        let body = Aabb{min:(68., 50.), max:(100., 82.)};
+        assert_eq!(clip_motion(&[&wall], &body, (MAX_PENETRATION, 0.)), (MAX_PENETRATION, 0.));
+    }
+
+    #[test]
+    fn fast_moves_stop_in_a_thin_stamp_instead_of_passing_through() {
+        let wall = rect((100., 0.), (104., 200.));
+        let body = Aabb{min:(58., 50.), max:(90., 82.)};
+        // far more than the wall is thick
+        let motion = clip_motion(&[&wall], &body, (30., 0.));
+        assert!(near(motion, (10. + MAX_PENETRATION, 0.)), "{:?}", motion);
+        let push = deepest_push(&[&wall], &body.moved(motion)).unwrap();
+        assert!(push.0 < 0., "pushed out the far side by {:?}", push);
+    }
+
+    #[test]
+    fn moves_into_a_wall_slide_along_it() {
+        let wall = rect((100., 0.), (104., 200.));
+        let body = Aabb{min:(66., 50.), max:(98., 82.)};
+        let motion = clip_motion(&[&wall], &body, (10., 10.));
+        assert!(motion.0 <= 2. + MAX_PENETRATION, "{:?}", motion);
+        assert!((motion.1 - 10.).abs() < 0.01, "{:?}", motion);
+    }
+
+    #[test]
+    fn moves_into_a_corner_stop_at_both_sides() {
+        let wall = rect((100., 0.), (104., 200.));
+        let floor = rect((0., 100.), (104., 104.));
+        let body = Aabb{min:(67., 67.), max:(99., 99.)};
+        let motion = clip_motion(&[&wall, &floor], &body, (20., 30.));
+        let moved = body.moved(motion);
+        assert!(moved.max.0 <= 100. + MAX_PENETRATION && moved.max.1 <= 100. + MAX_PENETRATION, "{:?}", moved);
+    }
+
+    #[test]
+    fn moves_wedged_into_a_v_stop_after_a_few_slides() {
+        // each slope turns the slide into the other one, more times than
+        // SLIDE_PASSES allows for
+        let left = Shape::new(vec![(0., 0.), (100., 100.), (0., 100.)]);
+        let right = Shape::new(vec![(200., 0.), (200., 100.), (100., 100.)]);
+        let body = Aabb{min:(84., 30.), max:(116., 62.)};
+        let motion = clip_motion(&[&left, &right], &body, (3., 60.));
+        let moved = body.moved(motion);
+        for shape in [&left, &right].iter() {
+            if let Some(push) = deepest_push(&[shape], &moved) {
+                assert!(length(push) <= MAX_PENETRATION + 0.01, "{:?} in {:?}", push, shape.outline);
+            }
+        }
+    }
+}
@@ -0,0 +1,216 @@
+// which keys do what, e.g.
+// <controls>
+//   <key name="A" actor="cat" action="left"/>
+//   <key name="Q" actor="cat" action="left"/>
+//   <key name="Escape" command="quit"/>
+// </controls>
+// keys are named the way SDL names them, after any of the modifiers Ctrl+ and
+// Shift+, as in Ctrl+Shift+Z; any number of keys may share a binding, and
+// some key has to quit, or there would be no way out of a fullscreen game
+use super::input::{Actor, Action};
+
+#[derive(Debug, Deserialize)]
+struct ControlsXml {
+    #[serde(rename="key", default)]
+    keys: Vec<KeyXml>,
+}
+
+#[derive(Debug, Deserialize)]
+struct KeyXml {
+    name: String,
+    actor: Option<String>,
+    action: Option<String>,
+    command: Option<String>,
+}
+
+// things keys do outside the simulation
+#[derive(Clone,Copy,Debug,PartialEq,Eq,Hash)]
+pub enum Command {
+    Quit,
+    Click,
+    SplitScreen,
+    NextLevel,
+    PreviousLevel,
+    PointerSteering,
+    // the level editor, and what its keys do while it is open
+    Editor,
+    NextStamp,
+    PreviousStamp,
+    Rotate,
+    ScaleUp,
+    ScaleDown,
+    NextColor,
+    Delete,
+    Snap,
+    Save,
+    Undo,
+    Redo,
+    // act on the stamp under the pointer rather than the one to be placed
+    TurnStamp,
+    RecolorStamp,
+}
+
+// commands by the name they go by in the config
+const COMMANDS: [(&str, Command); 20] = [
+    ("quit", Command::Quit),
+    ("click", Command::Click),
+    ("split_screen", Command::SplitScreen),
+    ("next_level", Command::NextLevel),
+    ("previous_level", Command::PreviousLevel),
+    ("pointer_steering", Command::PointerSteering),
+    ("editor", Command::Editor),
+    ("next_stamp", Command::NextStamp),
+    ("previous_stamp", Command::PreviousStamp),
+    ("rotate", Command::Rotate),
+    ("scale_up", Command::ScaleUp),
+    ("scale_down", Command::ScaleDown),
+    ("next_color", Command::NextColor),
+    ("delete", Command::Delete),
+    ("snap", Command::Snap),
+    ("save", Command::Save),
+    ("undo", Command::Undo),
+    ("redo", Command::Redo),
+    ("turn_stamp", Command::TurnStamp),
+    ("recolor_stamp", Command::RecolorStamp),
+];
+
+#[derive(Clone,Copy,Debug,PartialEq,Eq,Hash)]
+pub enum Binding {
+    Play(Actor, Action),
+    Command(Command),
+}
+
+#[derive(Clone, Debug)]
+pub struct Controls {
+    // key name and what it does, in the order given
+    pub keys: Vec<(String, Binding)>,
+}
+
+impl Default for Controls {
+    fn default() -> Self {
+        let keys = [
+            ("A", Binding::Play(Actor::Cat, Action::Left)),
+            ("D", Binding::Play(Actor::Cat, Action::Right)),
+            ("W", Binding::Play(Actor::Cat, Action::Jump)),
+            ("S", Binding::Play(Actor::Cat, Action::Down)),
+            ("Left", Binding::Play(Actor::Mouse, Action::Left)),
+            ("Right", Binding::Play(Actor::Mouse, Action::Right)),
+            ("Up", Binding::Play(Actor::Mouse, Action::Jump)),
+            ("Down", Binding::Play(Actor::Mouse, Action::Down)),
+            ("Escape", Binding::Command(Command::Quit)),
+            ("Keypad Enter", Binding::Command(Command::Click)),
+            ("F2", Binding::Command(Command::SplitScreen)),
+            ("PageDown", Binding::Command(Command::NextLevel)),
+            ("PageUp", Binding::Command(Command::PreviousLevel)),
+            ("F3", Binding::Command(Command::PointerSteering)),
+            ("F4", Binding::Command(Command::Editor)),
+            ("]", Binding::Command(Command::NextStamp)),
+            ("[", Binding::Command(Command::PreviousStamp)),
+            ("R", Binding::Command(Command::Rotate)),
+            ("=", Binding::Command(Command::ScaleUp)),
+            ("-", Binding::Command(Command::ScaleDown)),
+            ("C", Binding::Command(Command::NextColor)),
+            ("Delete", Binding::Command(Command::Delete)),
+            ("G", Binding::Command(Command::Snap)),
+            ("F5", Binding::Command(Command::Save)),
+            ("Ctrl+Z", Binding::Command(Command::Undo)),
+            ("Ctrl+Shift+Z", Binding::Command(Command::Redo)),
+            ("Shift+R", Binding::Command(Command::TurnStamp)),
+            ("Shift+C", Binding::Command(Command::RecolorStamp)),
+        ];
+        Controls{
+            keys:keys.iter().map(|&(name, binding)| (name.to_string(), binding)).collect(),
+        }
+    }
+}
+
+impl std::str::FromStr for Controls {
+    type Err = String;
+    fn from_str(config: &str) -> Result<Self, String> {
+        let xml: ControlsXml = serde_xml_rs::from_str(config).map_err(|err| format!("bad controls: {:?}", err))?;
+        let mut keys = Vec::new();
+        for key in xml.keys {
+            let binding = match (key.actor, key.action, key.command) {
+                (Some(actor), Some(action), None) => Binding::Play(actor_named(&actor)?, action_named(&action)?),
+                (None, None, Some(command)) => Binding::Command(command_named(&command)?),
+                _ => return Err(format!("key {} needs either an actor and an action, or a command", key.name)),
+            };
+            keys.push((key.name, binding));
+        }
+        if !keys.iter().any(|&(_, binding)| binding == Binding::Command(Command::Quit)) {
+            return Err("no key has the quit command".to_string());
+        }
+        Ok(Controls{keys})
+    }
+}
+
+fn actor_named(name: &str) -> Result<Actor, String> {
+    match name {
+        "cat" => Ok(Actor::Cat),
+        "mouse" => Ok(Actor::Mouse),
+        _ => Err(format!("unknown actor {}, expected cat or mouse", name)),
+    }
+}
+
+fn action_named(name: &str) -> Result<Action, String> {
+    match name {
+        "left" => Ok(Action::Left),
+        "right" => Ok(Action::Right),
+        "jump" => Ok(Action::Jump),
+        "down" => Ok(Action::Down),
+        _ => Err(format!("unknown action {}, expected left, right, jump or down", name)),
+    }
+}
+
+fn command_named(name: &str) -> Result<Command, String> {
+    match COMMANDS.iter().find(|&&(command_name, _)| command_name == name) {
+        Some(&(_, command)) => Ok(command),
+        None => {
+            let names: Vec<&str> = COMMANDS.iter().map(|&(command_name, _)| command_name).collect();
+            Err(format!("unknown command {}, expected one of {}", name, names.join(", ")))
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_shipped_controls_are_the_defaults() {
+        let controls = std::fs::read_to_string("assets/controls.xml").unwrap().parse::<Controls>().unwrap();
+        assert_eq!(controls.keys, Controls::default().keys);
+    }
+
+    #[test]
+    fn keys_can_share_a_binding() {
+        let controls = "<controls>
+  <key name=\"A\" actor=\"cat\" action=\"left\"/>
+  <key name=\"Q\" actor=\"cat\" action=\"left\"/>
+  <key name=\"Escape\" command=\"quit\"/>
+</controls>".parse::<Controls>().unwrap();
+        assert_eq!(controls.keys, vec![
+            ("A".to_string(), Binding::Play(Actor::Cat, Action::Left)),
+            ("Q".to_string(), Binding::Play(Actor::Cat, Action::Left)),
+            ("Escape".to_string(), Binding::Command(Command::Quit)),
+        ]);
+    }
+
+    #[test]
+    fn rejects_unknown_names() {
+        let parse = |key: &str| format!("<controls>{}<key name=\"Escape\" command=\"quit\"/></controls>", key).parse::<Controls>();
+        assert!(parse("").is_ok());
+        assert!(parse("<key name=\"A\" actor=\"dog\" action=\"left\"/>").is_err());
+        assert!(parse("<key name=\"A\" actor=\"cat\" action=\"fly\"/>").is_err());
+        assert!(parse("<key name=\"A\" command=\"dance\"/>").is_err());
+        // either a command or an actor and action, not both or neither
+        assert!(parse("<key name=\"A\" actor=\"cat\" action=\"left\" command=\"quit\"/>").is_err());
+        assert!(parse("<key name=\"A\" actor=\"cat\"/>").is_err());
+    }
+
+    #[test]
+    fn rejects_controls_without_quit() {
+        assert!("<controls></controls>".parse::<Controls>().is_err());
+        assert!("<controls><key name=\"A\" actor=\"cat\" action=\"left\"/></controls>".parse::<Controls>().is_err());
+    }
+}
@@ -0,0 +1,784 @@
+use art_stamps::{Transform, SVG, F64Point, Color, g};
+use super::collision::{self, Aabb, Contacts, ContactSide, Shape};
+use super::grid::ShapeGrid;
+use super::nav::NavGraph;
+use std::collections::HashMap;
+use std::path::Path;
+use super::input::{Actor, Action, Actions, Input, ACTORS};
+use super::level::{self, Level, centered_at, stamp_file_name};
+
+// movement tuning, in pixels and seconds
+pub const TICKS_PER_SECOND: u32 = 120;
+pub const SIM_TICK_SECONDS: f32 = 1.0 / TICKS_PER_SECOND as f32;
+pub const GRAVITY: f32 = 1400.0;
+const MOVE_ACCEL: f32 = 1800.0;
+const AIR_ACCEL: f32 = 900.0;
+const FRICTION: f32 = 2200.0;
+pub const MAX_SPEED: f32 = 260.0;
+const MAX_FALL_SPEED: f32 = 900.0;
+pub const JUMP_SPEED: f32 = 620.0;
+const FAST_FALL_ACCEL: f32 = 1200.0;
+// on ground steeper than this, about 37 degrees, friction gives way and bodies slide
+pub const SLIDE_NORMAL_Y: f32 = -0.8;
+
+// sprite sizes of the actors
+pub const MOUSE_SIZE: u32 = 32;
+pub const CAT_SIZE: u32 = 64;
+// how hard the actors are to shove around when they collide with each other
+const MOUSE_MASS: f32 = 1.0;
+const CAT_MASS: f32 = 4.0;
+
+// things that happened during a tick, for the rules and anything watching the game
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub enum GameEvent {
+    // the actors' bodies met; normal points from the cat to the mouse, and
+    // speed is how fast they were closing along it
+    ActorContact{normal:(f32, f32), speed:f32},
+}
+
+// which meetings of the actors' bodies end the round with the mouse caught
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum CatchRule {
+    // any touch at all
+    Touch,
+    // running into each other side on; either may stand on the other's head
+    SideOn,
+    // none: the bodies only push each other, for variants like sumo or co-op
+    Never,
+}
+
+#[derive(Clone,Debug)]
+pub struct LocationVel {
+    pub location:Transform,
+    pub vx:f32,
+    pub vy:f32,
+    // horizontal input in [-1, 1], set by the controls and consumed by sim
+    pub ax:f32,
+    pub fast_fall:bool,
+    pub contacts:Contacts,
+    // unit normal of the ground last stood on, pointing out of it
+    pub ground_normal:(f32, f32),
+    pub mass:f32,
+}
+
+impl LocationVel {
+    pub fn new(location: Transform, mass: f32) -> Self {
+        LocationVel{
+            location,
+            vx:0.,
+            vy:0.,
+            ax:0.,
+            fast_fall:false,
+            contacts:Contacts::default(),
+            ground_normal:(0., -1.),
+            mass,
+        }
+    }
+    pub fn jump(&mut self) {
+        if self.contacts.grounded {
+            self.vy = -JUMP_SPEED;
+            self.contacts.grounded = false;
+        }
+    }
+    pub fn center(&self) -> F64Point {
+        center(&self.location)
+    }
+    pub fn bounds(&self) -> Aabb {
+        Aabb::around(&self.location.to_bbox())
+    }
+    // jump_pressed is true only on the tick the jump action goes down
+    fn control(&mut self, actions: Actions, jump_pressed: bool) {
+        self.ax = actions.horizontal();
+        self.fast_fall = actions.contains(Action::Down);
+        if jump_pressed {
+            self.jump();
+        }
+    }
+    fn integrate(&mut self, shapes: &[&Shape], dt: f32) {
+        if self.contacts.grounded {
+            self.walk(dt);
+        } else if self.ax != 0. {
+            self.vx += self.ax * AIR_ACCEL * dt;
+            self.vx = self.vx.clamp(-MAX_SPEED, MAX_SPEED);
+        }
+        self.vy += GRAVITY * dt;
+        if self.fast_fall {
+            self.vy += FAST_FALL_ACCEL * dt;
+        }
+        self.vy = self.vy.min(MAX_FALL_SPEED);
+        let motion = collision::clip_motion(shapes, &self.bounds(), (f64::from(self.vx * dt), f64::from(self.vy * dt)));
+        self.location.tx += motion.0;
+        self.location.ty += motion.1;
+        // collisions re-establish contact with the ground every tick
+        self.contacts = Contacts::default();
+    }
+    // accelerates along the ground rather than horizontally, so slopes are
+    // walked up and, when too steep to stand on, slid down
+    fn walk(&mut self, dt: f32) {
+        let (nx, ny) = self.ground_normal;
+        let (tx, ty) = (-ny, nx);
+        let mut speed = self.vx * tx + self.vy * ty;
+        if self.ax != 0. {
+            speed += self.ax * MOVE_ACCEL * dt;
+        } else if ny <= SLIDE_NORMAL_Y {
+            // friction only brings the body to rest, it never reverses it
+            let slowdown = FRICTION * dt;
+            if speed.abs() <= slowdown {
+                speed = 0.;
+            } else {
+                speed -= slowdown * speed.signum();
+            }
+        }
+        speed = speed.clamp(-MAX_SPEED, MAX_SPEED);
+        self.vx = speed * tx;
+        self.vy = speed * ty;
+    }
+    // collision is the shortest push out of the level, so its direction is the surface normal
+    fn apply_collision(&mut self, collision: F64Point) {
+        let length = (collision.0 * collision.0 + collision.1 * collision.1).sqrt();
+        if length == 0. {
+            return;
+        }
+        let normal = ((collision.0 / length) as f32, (collision.1 / length) as f32);
+        self.touch(normal);
+        if normal.1 <= SLIDE_NORMAL_Y {
+            // lift straight up out of walkable ground, so bodies standing on a
+            // slope don't creep down it
+            self.location.ty -= length / f64::from(-normal.1);
+        } else {
+            self.location.tx += collision.0;
+            self.location.ty += collision.1;
+        }
+        // only the velocity along the surface survives
+        let into = self.vx * normal.0 + self.vy * normal.1;
+        if into < 0. {
+            self.vx -= into * normal.0;
+            self.vy -= into * normal.1;
+        }
+    }
+    // records a surface pushing the body along normal
+    fn touch(&mut self, normal: (f32, f32)) {
+        let side = ContactSide::of(normal);
+        self.contacts.add(side);
+        if side == ContactSide::Ground {
+            self.ground_normal = normal;
+        }
+    }
+}
+
+// round lengths, in sim ticks
+const COUNTDOWN_TICKS: u32 = 3 * TICKS_PER_SECOND;
+const ROUND_TICKS: u32 = 60 * TICKS_PER_SECOND;
+const ROUND_OVER_TICKS: u32 = 2 * TICKS_PER_SECOND;
+
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum RoundState {
+    Countdown{ticks_left: u32},
+    Playing{ticks_left: u32},
+    Caught{ticks_left: u32},
+    MouseEscaped{ticks_left: u32},
+    Timeout{ticks_left: u32},
+}
+
+impl RoundState {
+    pub fn is_playing(&self) -> bool {
+        matches!(*self, RoundState::Playing{..})
+    }
+    // advances the countdown of the current state; None means the round should restart
+    fn tick(self) -> Option<RoundState> {
+        Some(match self {
+            RoundState::Countdown{ticks_left:0} => RoundState::Playing{ticks_left:ROUND_TICKS},
+            RoundState::Countdown{ticks_left} => RoundState::Countdown{ticks_left:ticks_left - 1},
+            RoundState::Playing{ticks_left:0} => RoundState::Timeout{ticks_left:ROUND_OVER_TICKS},
+            RoundState::Playing{ticks_left} => RoundState::Playing{ticks_left:ticks_left - 1},
+            RoundState::Caught{ticks_left:0} | RoundState::MouseEscaped{ticks_left:0} | RoundState::Timeout{ticks_left:0} => return None,
+            RoundState::Caught{ticks_left} => RoundState::Caught{ticks_left:ticks_left - 1},
+            RoundState::MouseEscaped{ticks_left} => RoundState::MouseEscaped{ticks_left:ticks_left - 1},
+            RoundState::Timeout{ticks_left} => RoundState::Timeout{ticks_left:ticks_left - 1},
+        })
+    }
+    // fraction of the current state's duration that remains
+    pub fn remaining(&self) -> f64 {
+        match *self {
+            RoundState::Countdown{ticks_left} => f64::from(ticks_left) / f64::from(COUNTDOWN_TICKS),
+            RoundState::Playing{ticks_left} => f64::from(ticks_left) / f64::from(ROUND_TICKS),
+            RoundState::Caught{ticks_left} | RoundState::MouseEscaped{ticks_left} | RoundState::Timeout{ticks_left} => f64::from(ticks_left) / f64::from(ROUND_OVER_TICKS),
+        }
+    }
+}
+
+// the game state and rules, with no dependency on a window or input device
+pub struct SceneState{
+    mouse_location: LocationVel,
+    cat_location: LocationVel,
+    mouse_spawn: Transform,
+    cat_spawn: Transform,
+    round: RoundState,
+    // positions at the start of the latest tick, for interpolating the render
+    prev_mouse_location: Transform,
+    prev_cat_location: Transform,
+    // input of the previous tick, so presses can be told apart from holds
+    last_input: Input,
+    // recorded with replays so any randomness in the rules can be reproduced
+    seed: u64,
+    svg: SVG,
+    // the level's spawn and goal marker stamps, which goals and the spawns
+    // are worked out from
+    markers: Vec<g>,
+    goals: Vec<[F64Point;4]>,
+    cache: HashMap<String,Vec<F64Point>>,
+    // collision outlines of svg's stamps; None until the next tick rebuilds
+    // it after svg changed
+    grid: Option<ShapeGrid>,
+    // where actors can go in the level, for the computer players; built on
+    // first use, and made stale along with grid
+    nav: Option<NavGraph>,
+    // whether the actors block and push each other instead of overlapping
+    actor_collision: bool,
+    catch_rule: CatchRule,
+    // what happened during the latest tick
+    events: Vec<GameEvent>,
+}
+
+impl SceneState {
+    pub fn new(level: Level, seed: u64) -> Self {
+        let (mouse_spawn, cat_spawn) = spawns(level.mouse_spawn, level.cat_spawn);
+        SceneState{
+            mouse_location:LocationVel::new(mouse_spawn.clone(), MOUSE_MASS),
+            cat_location:LocationVel::new(cat_spawn.clone(), CAT_MASS),
+            prev_mouse_location:mouse_spawn.clone(),
+            prev_cat_location:cat_spawn.clone(),
+            mouse_spawn,
+            cat_spawn,
+            round:RoundState::Countdown{ticks_left:COUNTDOWN_TICKS},
+            last_input:Input::default(),
+            seed,
+            svg:level.svg,
+            markers:level.markers,
+            goals:level.goals,
+            cache:HashMap::<String, Vec<F64Point>>::new(),
+            grid:None,
+            nav:None,
+            actor_collision:false,
+            catch_rule:CatchRule::Touch,
+            events:Vec::new(),
+        }
+    }
+    // swaps in another level and starts a fresh round on it; its stamp
+    // outlines are loaded again on first use, or by load_polygons
+    pub fn load_level(&mut self, level: Level) {
+        let (mouse_spawn, cat_spawn) = spawns(level.mouse_spawn, level.cat_spawn);
+        self.mouse_spawn = mouse_spawn;
+        self.cat_spawn = cat_spawn;
+        self.svg = level.svg;
+        self.markers = level.markers;
+        self.goals = level.goals;
+        self.cache.clear();
+        self.grid = None;
+        self.last_input = Input::default();
+        self.reset_round();
+    }
+    // loads the collision outline of every stamp in the level from assets_dir,
+    // instead of the path in the stamp's href
+    pub fn load_polygons(&mut self, assets_dir: &Path) -> Result<(), String> {
+        let urls: Vec<String> = self.svg.stamps.iter().map(|stamp| stamp.rect.href.url.clone()).collect();
+        for url in urls {
+            self.load_polygon(&url, assets_dir)?;
+        }
+        self.grid = None;
+        Ok(())
+    }
+    // the same for one stamp url, e.g. before a stamp of it is inserted
+    pub fn load_polygon(&mut self, url: &str, assets_dir: &Path) -> Result<(), String> {
+        if !self.cache.contains_key(url) {
+            // load_polygon maps <dir>/stamps/<name>.bmp to <dir>/<name>.svg
+            let bmp_path = assets_dir.join("stamps").join(stamp_file_name(url));
+            let polygon = self.svg.load_polygon(&bmp_path.to_string_lossy())
+                .map_err(|err| format!("{}: {:?}", url, err))?;
+            self.cache.insert(url.to_string(), polygon);
+        }
+        Ok(())
+    }
+    pub fn svg(&self) -> &SVG {
+        &self.svg
+    }
+    // all changes to the level go through here, so collision picks them up
+    pub fn svg_mut(&mut self) -> &mut SVG {
+        self.grid = None;
+        &mut self.svg
+    }
+    // puts stamp into the level at index, among svg().stamps; only the
+    // stamp's own outline is worked out, not the whole grid again
+    pub fn insert_stamp(&mut self, index: usize, stamp: g) -> Result<(), String> {
+        let shape = collision::stamp_shape(&self.svg, &stamp, &mut self.cache)?;
+        self.svg.stamps.insert(index, stamp);
+        if let Some(ref mut grid) = self.grid {
+            grid.insert_stamp(index, shape);
+        }
+        self.nav = None;
+        Ok(())
+    }
+    pub fn remove_stamp(&mut self, index: usize) -> g {
+        if let Some(ref mut grid) = self.grid {
+            grid.remove_stamp(index);
+        }
+        self.nav = None;
+        self.svg.stamps.remove(index)
+    }
+    // moves, turns or sizes the stamp at index, returning where it was
+    pub fn set_stamp_transform(&mut self, index: usize, transform: Transform) -> Result<Transform, String> {
+        let previous = std::mem::replace(&mut self.svg.stamps[index].transform, transform);
+        let shape = match collision::stamp_shape(&self.svg, &self.svg.stamps[index], &mut self.cache) {
+            Ok(shape) => shape,
+            Err(err) => {
+                self.svg.stamps[index].transform = previous;
+                return Err(err);
+            },
+        };
+        if let Some(ref mut grid) = self.grid {
+            grid.replace_stamp(index, shape);
+        }
+        self.nav = None;
+        Ok(previous)
+    }
+    // color doesn't change collision, so nothing needs working out again
+    pub fn set_stamp_fill(&mut self, index: usize, fill: Color) -> Color {
+        std::mem::replace(&mut self.svg.stamps[index].rect.fill, fill)
+    }
+    pub fn markers(&self) -> &[g] {
+        &self.markers
+    }
+    // moving markers moves the spawns and goals with them; the actors stay
+    // where they are until the next round
+    pub fn insert_marker(&mut self, index: usize, marker: g) {
+        self.markers.insert(index, marker);
+        self.update_markers();
+    }
+    pub fn remove_marker(&mut self, index: usize) -> g {
+        let marker = self.markers.remove(index);
+        self.update_markers();
+        marker
+    }
+    fn update_markers(&mut self) {
+        let (cat_spawn, mouse_spawn, goals) = level::read_markers(&self.markers);
+        let (mouse_spawn, cat_spawn) = spawns(mouse_spawn, cat_spawn);
+        self.mouse_spawn = mouse_spawn;
+        self.cat_spawn = cat_spawn;
+        self.goals = goals;
+    }
+    // the level as it is now, markers included, as svg file contents
+    pub fn level_svg(&self, assets_dir: &Path) -> Result<String, String> {
+        level::write_level(&self.svg, &self.markers, assets_dir)
+    }
+    pub fn actor_collision(&self) -> bool {
+        self.actor_collision
+    }
+    // also sets the catch rule that goes with it: once the actors can stand
+    // on each other, only running into each other side on is a catch
+    pub fn set_actor_collision(&mut self, actor_collision: bool) {
+        self.actor_collision = actor_collision;
+        self.catch_rule = if actor_collision {CatchRule::SideOn} else {CatchRule::Touch};
+    }
+    pub fn catch_rule(&self) -> CatchRule {
+        self.catch_rule
+    }
+    pub fn set_catch_rule(&mut self, catch_rule: CatchRule) {
+        self.catch_rule = catch_rule;
+    }
+    // the events of the latest tick
+    pub fn events(&self) -> &[GameEvent] {
+        &self.events
+    }
+    // advances the game by one tick of SIM_TICK_SECONDS
+    pub fn sim(&mut self, input: &Input) -> Result<(), String> {
+        self.events.clear();
+        self.prev_cat_location = self.cat_location.location.clone();
+        self.prev_mouse_location = self.mouse_location.location.clone();
+        // controls only work while the round is being played
+        let input = if self.round.is_playing() {*input} else {Input::default()};
+        for &actor in ACTORS.iter() {
+            let actions = input.actions(actor);
+            let jump_pressed = actions.contains(Action::Jump) && !self.last_input.actions(actor).contains(Action::Jump);
+            self.actor_mut(actor).control(actions, jump_pressed);
+        }
+        self.last_input = input;
+        self.update_grid()?;
+        if let Some(ref grid) = self.grid {
+            // the only shapes either body can reach this tick
+            let cat_near = grid.near(&self.cat_location.bounds().expanded(NEAR_MARGIN));
+            let mouse_near = grid.near(&self.mouse_location.bounds().expanded(NEAR_MARGIN));
+            self.cat_location.integrate(&cat_near, SIM_TICK_SECONDS);
+            self.mouse_location.integrate(&mouse_near, SIM_TICK_SECONDS);
+            collide_with_level(&cat_near, &mut self.cat_location);
+            collide_with_level(&mouse_near, &mut self.mouse_location);
+            if let Some(contact) = collide_actors(&mut self.cat_location, &mut self.mouse_location, self.actor_collision) {
+                self.events.push(contact);
+                if self.actor_collision {
+                    // a shove may have pushed either of them into the level
+                    collide_with_level(&cat_near, &mut self.cat_location);
+                    collide_with_level(&mouse_near, &mut self.mouse_location);
+                }
+            }
+        }
+        self.update_round();
+        Ok(())
+    }
+    // rebuilds the collision outlines if the level changed since the last time
+    fn update_grid(&mut self) -> Result<(), String> {
+        if self.grid.is_none() {
+            self.grid = Some(ShapeGrid::build(&self.svg, &mut self.cache)?);
+            self.nav = None;
+        }
+        Ok(())
+    }
+    pub fn nav(&mut self) -> Result<&NavGraph, String> {
+        self.update_grid()?;
+        if self.nav.is_none() {
+            if let Some(ref grid) = self.grid {
+                self.nav = Some(NavGraph::build(grid, self.svg.width, self.svg.height));
+            }
+        }
+        self.nav.as_ref().ok_or_else(|| "no navigation graph".to_string())
+    }
+    // regions the mouse escapes through
+    pub fn goals(&self) -> &[[F64Point;4]] {
+        &self.goals
+    }
+    pub fn round(&self) -> RoundState {
+        self.round
+    }
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+    pub fn actor(&self, actor: Actor) -> &LocationVel {
+        match actor {
+            Actor::Cat => &self.cat_location,
+            Actor::Mouse => &self.mouse_location,
+        }
+    }
+    fn actor_mut(&mut self, actor: Actor) -> &mut LocationVel {
+        match actor {
+            Actor::Cat => &mut self.cat_location,
+            Actor::Mouse => &mut self.mouse_location,
+        }
+    }
+    // where the actor is drawn alpha of the way through the tick after the latest one
+    pub fn interpolated(&self, actor: Actor, alpha: f64) -> Transform {
+        let (prev, cur) = match actor {
+            Actor::Cat => (&self.prev_cat_location, &self.cat_location.location),
+            Actor::Mouse => (&self.prev_mouse_location, &self.mouse_location.location),
+        };
+        let mut ret = cur.clone();
+        ret.tx = prev.tx + (cur.tx - prev.tx) * alpha;
+        ret.ty = prev.ty + (cur.ty - prev.ty) * alpha;
+        ret
+    }
+    // puts both actors back on their spawn points and starts the countdown again
+    pub fn reset_round(&mut self) {
+        self.cat_location = LocationVel::new(self.cat_spawn.clone(), CAT_MASS);
+        self.mouse_location = LocationVel::new(self.mouse_spawn.clone(), MOUSE_MASS);
+        self.prev_cat_location = self.cat_spawn.clone();
+        self.prev_mouse_location = self.mouse_spawn.clone();
+        self.round = RoundState::Countdown{ticks_left:COUNTDOWN_TICKS};
+    }
+    // whether the actors met during the latest tick in a way the catch rule
+    // counts; with actor collision on the bodies never overlap, so the
+    // contact events are what tell
+    pub fn mouse_caught(&self) -> bool {
+        let contact = |side_on: bool| self.events.iter().any(|event| match *event {
+            GameEvent::ActorContact{normal, ..} => !side_on || normal.1 == 0.,
+        });
+        match self.catch_rule {
+            CatchRule::Touch => contact(false) ||
+                art_stamps::poly_edge_intersect(&self.cat_location.location.to_bbox(), &self.mouse_location.location.to_bbox()),
+            CatchRule::SideOn => contact(true),
+            CatchRule::Never => false,
+        }
+    }
+    // the mouse escapes by reaching a goal, or by leaving a level that has none
+    pub fn mouse_escaped(&self) -> bool {
+        let mouse_bbox = self.mouse_location.location.to_bbox();
+        if !self.goals.is_empty() {
+            return self.goals.iter().any(|goal| art_stamps::poly_edge_intersect(goal, &mouse_bbox));
+        }
+        let width = f64::from(self.svg.width);
+        let height = f64::from(self.svg.height);
+        mouse_bbox.iter().all(
+            |&(x, y)| x < 0. || y < 0. || x > width || y > height)
+    }
+    // a camera centered on focus that never shows past the edges of the level;
+    // levels smaller than the view are centered in it instead
+    pub fn camera_for(&self, focus: F64Point, view_width: u32, view_height: u32) -> Transform {
+        let mut camera = Transform::new(0,0);
+        camera.tx = -camera_offset(focus.0, f64::from(view_width), f64::from(self.svg.width));
+        camera.ty = -camera_offset(focus.1, f64::from(view_height), f64::from(self.svg.height));
+        camera
+    }
+    fn update_round(&mut self) {
+        if self.round.is_playing() {
+            if self.mouse_caught() {
+                self.round = RoundState::Caught{ticks_left:ROUND_OVER_TICKS};
+            } else if self.mouse_escaped() {
+                self.round = RoundState::MouseEscaped{ticks_left:ROUND_OVER_TICKS};
+            }
+        }
+        match self.round.tick() {
+            Some(next) => self.round = next,
+            None => self.reset_round(),
+        }
+    }
+}
+
+// levels without spawn markers start the actors in the top left corner
+fn spawns(mouse_spawn: Option<F64Point>, cat_spawn: Option<F64Point>) -> (Transform, Transform) {
+    (centered_at(mouse_spawn.unwrap_or((32., 32.)), MOUSE_SIZE, MOUSE_SIZE),
+     centered_at(cat_spawn.unwrap_or((96., 96.)), CAT_SIZE, CAT_SIZE))
+}
+
+pub fn center(location: &Transform) -> F64Point {
+    art_stamps::ftransform(location, (location.midx, location.midy))
+}
+
+// each pass pushes the body out of its deepest overlap; bodies wedged
+// between several surfaces need a few
+const COLLISION_PASSES: usize = 4;
+// how far around a body to look for shapes it could touch within a tick:
+// more than it can move, plus room for pushing it back out
+const NEAR_MARGIN: f64 = 32.0;
+
+fn collide_with_level(shapes: &[&Shape], body: &mut LocationVel) {
+    for _ in 0..COLLISION_PASSES {
+        let push = match collision::deepest_push(shapes, &body.bounds()) {
+            Some(push) => push,
+            None => break,
+        };
+        body.apply_collision(push);
+    }
+}
+
+// reports the actors' bodies meeting and, with push set, separates them:
+// each gives way in proportion to the other's mass, and both end up moving
+// together along the contact normal, as in a perfectly inelastic collision
+fn collide_actors(cat: &mut LocationVel, mouse: &mut LocationVel, push: bool) -> Option<GameEvent> {
+    let (a, b) = (cat.bounds(), mouse.bounds());
+    if !a.overlaps(&b) {
+        return None;
+    }
+    // overlap along each axis, signed to point from the cat to the mouse
+    let dx = if b.min.0 + b.max.0 > a.min.0 + a.max.0 {a.max.0 - b.min.0} else {a.min.0 - b.max.0};
+    let dy = if b.min.1 + b.max.1 > a.min.1 + a.max.1 {a.max.1 - b.min.1} else {a.min.1 - b.max.1};
+    let (normal, depth) = if dx.abs() < dy.abs() {
+        ((dx.signum() as f32, 0.), dx.abs())
+    } else {
+        ((0., dy.signum() as f32), dy.abs())
+    };
+    let cat_speed = cat.vx * normal.0 + cat.vy * normal.1;
+    let mouse_speed = mouse.vx * normal.0 + mouse.vy * normal.1;
+    let closing = cat_speed - mouse_speed;
+    if push {
+        let total = cat.mass + mouse.mass;
+        let cat_share = depth * f64::from(mouse.mass / total);
+        let mouse_share = depth - cat_share;
+        cat.location.tx -= f64::from(normal.0) * cat_share;
+        cat.location.ty -= f64::from(normal.1) * cat_share;
+        mouse.location.tx += f64::from(normal.0) * mouse_share;
+        mouse.location.ty += f64::from(normal.1) * mouse_share;
+        if closing > 0. {
+            let shared = (cat.mass * cat_speed + mouse.mass * mouse_speed) / total;
+            cat.vx += (shared - cat_speed) * normal.0;
+            cat.vy += (shared - cat_speed) * normal.1;
+            mouse.vx += (shared - mouse_speed) * normal.0;
+            mouse.vy += (shared - mouse_speed) * normal.1;
+        }
+        // so an actor can stand and jump on the other's head
+        cat.touch((-normal.0, -normal.1));
+        mouse.touch(normal);
+    }
+    Some(GameEvent::ActorContact{normal, speed:closing.max(0.)})
+}
+
+// how far the camera scrolls along one axis to center focus within the level bounds
+fn camera_offset(focus: f64, view: f64, level: f64) -> f64 {
+    if level <= view {
+        return (level - view) * 0.5;
+    }
+    (focus - view * 0.5).max(0.).min(level - view)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::level::{fixtures, CAT_SPAWN_STAMP, MOUSE_SPAWN_STAMP, GOAL_STAMP};
+
+    // a level 1024 wide with a floor of square stamps along y = 640, plus
+    // any markers; outlines load from the shipped assets
+    fn floor_level(markers: &[(&str, f64, f64)]) -> SceneState {
+        let mut body = String::new();
+        for x in (0..1024).step_by(64) {
+            body += &fixtures::stamp("square", x as f64, 640., 64);
+        }
+        for &(name, x, y) in markers {
+            let size = match name {
+                MOUSE_SPAWN_STAMP => MOUSE_SIZE,
+                _ => CAT_SIZE,
+            };
+            body += &fixtures::stamp(name, x, y, size);
+        }
+        fixtures::scene(&fixtures::level_data(1024, 768, &body), 1)
+    }
+
+    // runs out the countdown with nobody moving
+    fn start(scene: &mut SceneState) {
+        while !scene.round().is_playing() {
+            scene.sim(&Input::default()).unwrap();
+        }
+    }
+
+    fn holding(actor: Actor, actions: &[Action]) -> Input {
+        let mut input = Input::default();
+        for &action in actions {
+            input.actions_mut(actor).insert(action);
+        }
+        input
+    }
+
+    #[test]
+    fn actors_land_on_the_floor() {
+        let mut scene = floor_level(&[(MOUSE_SPAWN_STAMP, 300., 500.), (CAT_SPAWN_STAMP, 600., 400.)]);
+        start(&mut scene);
+        for &actor in ACTORS.iter() {
+            let body = scene.actor(actor);
+            assert!(body.contacts.grounded, "{:?} is not on the ground", actor);
+            assert!((body.bounds().max.1 - 640.).abs() < 1., "{:?} rests at {}", actor, body.bounds().max.1);
+        }
+    }
+
+    #[test]
+    fn jumping_rises_and_lands_again() {
+        let mut scene = floor_level(&[(MOUSE_SPAWN_STAMP, 300., 600.), (CAT_SPAWN_STAMP, 800., 570.)]);
+        start(&mut scene);
+        let ground = scene.actor(Actor::Mouse).center().1;
+        scene.sim(&holding(Actor::Mouse, &[Action::Jump])).unwrap();
+        let mut highest = ground;
+        // holding jump down doesn't jump again on landing
+        for _ in 0..TICKS_PER_SECOND * 2 {
+            scene.sim(&holding(Actor::Mouse, &[Action::Jump])).unwrap();
+            highest = highest.min(scene.actor(Actor::Mouse).center().1);
+        }
+        let peak = f64::from(JUMP_SPEED * JUMP_SPEED / (2. * GRAVITY));
+        assert!((ground - highest - peak).abs() < 8., "jumped {} instead of about {}", ground - highest, peak);
+        assert!(scene.actor(Actor::Mouse).contacts.grounded);
+        assert!((scene.actor(Actor::Mouse).center().1 - ground).abs() < 1.);
+    }
+
+    #[test]
+    fn running_reaches_full_speed() {
+        let mut scene = floor_level(&[(MOUSE_SPAWN_STAMP, 100., 600.), (CAT_SPAWN_STAMP, 900., 570.)]);
+        start(&mut scene);
+        let from = scene.actor(Actor::Mouse).center().0;
+        for _ in 0..TICKS_PER_SECOND {
+            scene.sim(&holding(Actor::Mouse, &[Action::Right])).unwrap();
+        }
+        assert!((scene.actor(Actor::Mouse).vx - MAX_SPEED).abs() < 0.01, "runs at {}", scene.actor(Actor::Mouse).vx);
+        // a second of running, less the time spent getting up to speed
+        let ran = scene.actor(Actor::Mouse).center().0 - from;
+        let expected = f64::from(MAX_SPEED - MAX_SPEED * MAX_SPEED / (2. * MOVE_ACCEL));
+        assert!((ran - expected).abs() < 4., "ran {} instead of about {}", ran, expected);
+    }
+
+    #[test]
+    fn controls_wait_for_the_countdown() {
+        let mut scene = floor_level(&[(MOUSE_SPAWN_STAMP, 300., 600.), (CAT_SPAWN_STAMP, 800., 570.)]);
+        let from = scene.actor(Actor::Mouse).center().0;
+        for _ in 0..COUNTDOWN_TICKS {
+            assert!(matches!(scene.round(), RoundState::Countdown{..}));
+            scene.sim(&holding(Actor::Mouse, &[Action::Right])).unwrap();
+        }
+        assert_eq!(scene.actor(Actor::Mouse).center().0, from);
+        scene.sim(&Input::default()).unwrap();
+        assert_eq!(scene.round(), RoundState::Playing{ticks_left:ROUND_TICKS});
+    }
+
+    #[test]
+    fn rounds_time_out_and_start_over() {
+        let mut scene = floor_level(&[(MOUSE_SPAWN_STAMP, 100., 600.), (CAT_SPAWN_STAMP, 800., 570.)]);
+        start(&mut scene);
+        for _ in 0..ROUND_TICKS {
+            scene.sim(&holding(Actor::Mouse, &[Action::Right])).unwrap();
+            if !scene.round().is_playing() {
+                break;
+            }
+        }
+        // the mouse ran into the cat on the way
+        assert!(matches!(scene.round(), RoundState::Caught{..}));
+        let mut scene = floor_level(&[(MOUSE_SPAWN_STAMP, 100., 600.), (CAT_SPAWN_STAMP, 800., 570.)]);
+        start(&mut scene);
+        for _ in 0..ROUND_TICKS + 1 {
+            scene.sim(&Input::default()).unwrap();
+        }
+        assert!(matches!(scene.round(), RoundState::Timeout{..}));
+        for _ in 0..ROUND_OVER_TICKS + 1 {
+            scene.sim(&Input::default()).unwrap();
+        }
+        assert!(matches!(scene.round(), RoundState::Countdown{..}));
+    }
+
+    #[test]
+    fn the_cat_catches_the_mouse() {
+        let mut scene = floor_level(&[(MOUSE_SPAWN_STAMP, 300., 600.), (CAT_SPAWN_STAMP, 500., 570.)]);
+        start(&mut scene);
+        for _ in 0..TICKS_PER_SECOND * 2 {
+            scene.sim(&holding(Actor::Cat, &[Action::Left])).unwrap();
+        }
+        assert!(matches!(scene.round(), RoundState::Caught{..}));
+        // then both go back to their spawns
+        for _ in 0..ROUND_OVER_TICKS {
+            scene.sim(&Input::default()).unwrap();
+        }
+        assert!(matches!(scene.round(), RoundState::Countdown{..}));
+        assert!(scene.actor(Actor::Mouse).center().0 < 400.);
+        assert!(scene.actor(Actor::Cat).center().0 > 500.);
+    }
+
+    #[test]
+    fn with_pushing_the_mouse_can_stand_on_the_cat() {
+        let mut scene = floor_level(&[(MOUSE_SPAWN_STAMP, 516., 500.), (CAT_SPAWN_STAMP, 500., 570.)]);
+        scene.set_actor_collision(true);
+        start(&mut scene);
+        for _ in 0..TICKS_PER_SECOND * 2 {
+            scene.sim(&Input::default()).unwrap();
+        }
+        assert!(scene.round().is_playing(), "{:?}", scene.round());
+        let (cat, mouse) = (scene.actor(Actor::Cat).bounds(), scene.actor(Actor::Mouse).bounds());
+        assert!((mouse.max.1 - cat.min.1).abs() < 1., "the mouse rests at {} on a cat whose head is at {}", mouse.max.1, cat.min.1);
+        assert!(scene.actor(Actor::Mouse).contacts.grounded);
+        // running into it is still a catch
+        let mut scene = floor_level(&[(MOUSE_SPAWN_STAMP, 300., 600.), (CAT_SPAWN_STAMP, 500., 570.)]);
+        scene.set_actor_collision(true);
+        start(&mut scene);
+        for _ in 0..TICKS_PER_SECOND * 2 {
+            scene.sim(&holding(Actor::Cat, &[Action::Left])).unwrap();
+        }
+        assert!(matches!(scene.round(), RoundState::Caught{..}));
+    }
+
+    #[test]
+    fn the_mouse_escapes_through_a_goal() {
+        let mut scene = floor_level(&[(MOUSE_SPAWN_STAMP, 300., 600.), (CAT_SPAWN_STAMP, 800., 570.), (GOAL_STAMP, 100., 570.)]);
+        start(&mut scene);
+        for _ in 0..TICKS_PER_SECOND * 2 {
+            scene.sim(&holding(Actor::Mouse, &[Action::Left])).unwrap();
+        }
+        assert!(matches!(scene.round(), RoundState::MouseEscaped{..}));
+    }
+
+    #[test]
+    fn without_goals_the_mouse_escapes_off_the_edge() {
+        let mut scene = floor_level(&[(MOUSE_SPAWN_STAMP, 960., 600.), (CAT_SPAWN_STAMP, 100., 570.)]);
+        start(&mut scene);
+        for _ in 0..TICKS_PER_SECOND * 2 {
+            scene.sim(&holding(Actor::Mouse, &[Action::Right])).unwrap();
+        }
+        assert!(matches!(scene.round(), RoundState::MouseEscaped{..}));
+    }
+}
@@ -0,0 +1,146 @@
+// a uniform grid over the level's stamp outlines, so collision only looks at
+// the stamps near a body instead of every stamp in the level
+use art_stamps::{SVG, F64Point};
+use std::collections::HashMap;
+use super::collision::{self, Aabb, Shape};
+
+// a couple of stamps across, so most outlines land in a handful of cells
+const CELL_SIZE: f64 = 128.0;
+
+pub struct ShapeGrid {
+    // indexed like the svg's stamps; None for stamps without an outline
+    shapes: Vec<Option<Shape>>,
+    cells: HashMap<(i32, i32), Vec<usize>>,
+}
+
+impl ShapeGrid {
+    // outlines missing from cache are loaded and added to it
+    pub fn build(svg: &SVG, cache: &mut HashMap<String,Vec<F64Point>>) -> Result<Self, String> {
+        let mut grid = ShapeGrid{
+            shapes:Vec::with_capacity(svg.stamps.len()),
+            cells:HashMap::new(),
+        };
+        for (index, stamp) in svg.stamps.iter().enumerate() {
+            let shape = collision::stamp_shape(svg, stamp, cache)?;
+            if let Some(ref shape) = shape {
+                grid.insert(index, &shape.bounds);
+            }
+            grid.shapes.push(shape);
+        }
+        Ok(grid)
+    }
+    // every shape whose bounds might overlap area, each once
+    pub fn near(&self, area: &Aabb) -> Vec<&Shape> {
+        let mut indices = Vec::new();
+        for cell in cells_under(area) {
+            if let Some(stamps) = self.cells.get(&cell) {
+                indices.extend_from_slice(stamps);
+            }
+        }
+        indices.sort_unstable();
+        indices.dedup();
+        indices.into_iter()
+            .filter_map(|index| self.shapes[index].as_ref())
+            .filter(|shape| shape.bounds.overlaps(area))
+            .collect()
+    }
+    // keeps the grid in step with a stamp put into the svg at index, so an
+    // edit doesn't mean building the whole grid again
+    pub fn insert_stamp(&mut self, index: usize, shape: Option<Shape>) {
+        for stamps in self.cells.values_mut() {
+            for stamp in stamps.iter_mut().filter(|stamp| **stamp >= index) {
+                *stamp += 1;
+            }
+        }
+        if let Some(ref shape) = shape {
+            self.insert(index, &shape.bounds);
+        }
+        self.shapes.insert(index, shape);
+    }
+    pub fn remove_stamp(&mut self, index: usize) {
+        if let Some(shape) = self.shapes.remove(index) {
+            self.remove(index, &shape.bounds);
+        }
+        for stamps in self.cells.values_mut() {
+            for stamp in stamps.iter_mut().filter(|stamp| **stamp > index) {
+                *stamp -= 1;
+            }
+        }
+    }
+    // the stamp at index moved, turned or changed size
+    pub fn replace_stamp(&mut self, index: usize, shape: Option<Shape>) {
+        if let Some(old) = self.shapes[index].take() {
+            self.remove(index, &old.bounds);
+        }
+        if let Some(ref shape) = shape {
+            self.insert(index, &shape.bounds);
+        }
+        self.shapes[index] = shape;
+    }
+    fn insert(&mut self, index: usize, bounds: &Aabb) {
+        for cell in cells_under(bounds) {
+            self.cells.entry(cell).or_default().push(index);
+        }
+    }
+    fn remove(&mut self, index: usize, bounds: &Aabb) {
+        for cell in cells_under(bounds) {
+            if let Some(stamps) = self.cells.get_mut(&cell) {
+                stamps.retain(|&stamp| stamp != index);
+            }
+        }
+    }
+}
+
+fn cells_under(area: &Aabb) -> Vec<(i32, i32)> {
+    let min = ((area.min.0 / CELL_SIZE).floor() as i32, (area.min.1 / CELL_SIZE).floor() as i32);
+    let max = ((area.max.0 / CELL_SIZE).floor() as i32, (area.max.1 / CELL_SIZE).floor() as i32);
+    let mut ret = Vec::new();
+    for y in min.1..=max.1 {
+        for x in min.0..=max.0 {
+            ret.push((x, y));
+        }
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::level::fixtures;
+
+    fn outlines(shapes: Vec<&Shape>) -> Vec<Vec<F64Point>> {
+        shapes.into_iter().map(|shape| shape.outline.clone()).collect()
+    }
+
+    #[test]
+    fn edits_match_building_again() {
+        let stamps = [
+            fixtures::stamp("square", 10., 10., 64),
+            // across several cells
+            fixtures::stamp("square", 100., 100., 300),
+            // left of and above the origin
+            fixtures::stamp("square", -200., -150., 64),
+        ].concat();
+        let mut svg = fixtures::level(&fixtures::level_data(1024, 768, &stamps)).svg;
+        let mut cache = HashMap::new();
+        let mut grid = ShapeGrid::build(&svg, &mut cache).unwrap();
+        let mut extra = fixtures::level(&fixtures::level_data(1024, 768, &fixtures::stamp("ramp", 250., -40., 128))).svg;
+        let stamp = extra.stamps.remove(0);
+        grid.insert_stamp(1, collision::stamp_shape(&svg, &stamp, &mut cache).unwrap());
+        svg.stamps.insert(1, stamp);
+        svg.stamps[2].transform.tx -= 500.;
+        grid.replace_stamp(2, collision::stamp_shape(&svg, &svg.stamps[2], &mut cache).unwrap());
+        grid.remove_stamp(0);
+        svg.stamps.remove(0);
+        let built = ShapeGrid::build(&svg, &mut cache).unwrap();
+        for y in (-400..800).step_by(100) {
+            for x in (-600..800).step_by(100) {
+                let area = Aabb{min:(f64::from(x), f64::from(y)), max:(f64::from(x) + 150., f64::from(y) + 150.)};
+                assert_eq!(outlines(grid.near(&area)), outlines(built.near(&area)), "near {:?}", area);
+            }
+        }
+        // the edits are all there to be found
+        let everything = Aabb{min:(-1000., -1000.), max:(2000., 2000.)};
+        assert_eq!(grid.near(&everything).len(), 3);
+    }
+}
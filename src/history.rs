@@ -0,0 +1,131 @@
+// undo and redo for level edits: applying an edit to the scene gives back the
+// edit that undoes it, so undoing and redoing are the same thing
+use art_stamps::{Transform, Color, g};
+use std::collections::VecDeque;
+use super::game::SceneState;
+
+// edits further back than this are forgotten
+const HISTORY_LIMIT: usize = 256;
+
+pub enum Edit {
+    // stamp goes in at index, among the markers or among the level's stamps
+    Insert{marker: bool, index: usize, stamp: g},
+    Remove{marker: bool, index: usize},
+    // the level stamp at index is placed by transform, or painted fill
+    Transform{index: usize, transform: Transform},
+    Recolor{index: usize, fill: Color},
+}
+
+impl Edit {
+    // makes the edit, returning the one that undoes it
+    pub fn apply(self, scene: &mut SceneState) -> Result<Edit, String> {
+        Ok(match self {
+            Edit::Insert{marker:true, index, stamp} => {
+                scene.insert_marker(index, stamp);
+                Edit::Remove{marker:true, index}
+            },
+            Edit::Insert{marker:false, index, stamp} => {
+                scene.insert_stamp(index, stamp)?;
+                Edit::Remove{marker:false, index}
+            },
+            Edit::Remove{marker:true, index} => Edit::Insert{marker:true, index, stamp:scene.remove_marker(index)},
+            Edit::Remove{marker:false, index} => Edit::Insert{marker:false, index, stamp:scene.remove_stamp(index)},
+            Edit::Transform{index, transform} => Edit::Transform{index, transform:scene.set_stamp_transform(index, transform)?},
+            Edit::Recolor{index, fill} => Edit::Recolor{index, fill:scene.set_stamp_fill(index, fill)},
+        })
+    }
+}
+
+// the edits made to one level; indices in them only hold as long as every
+// edit goes through here, so it is cleared when another level is loaded
+#[derive(Default)]
+pub struct History {
+    // edits undoing what was done, latest last
+    undo: VecDeque<Edit>,
+    // edits redoing what was undone, latest last
+    redo: Vec<Edit>,
+}
+
+impl History {
+    pub fn apply(&mut self, scene: &mut SceneState, edit: Edit) -> Result<(), String> {
+        let inverse = edit.apply(scene)?;
+        self.redo.clear();
+        if self.undo.len() == HISTORY_LIMIT {
+            self.undo.pop_front();
+        }
+        self.undo.push_back(inverse);
+        Ok(())
+    }
+    // false when there was nothing to undo; an edit that fails is dropped,
+    // since it would fail again, so the ones before it can still be undone
+    pub fn undo(&mut self, scene: &mut SceneState) -> Result<bool, String> {
+        let edit = match self.undo.pop_back() {
+            Some(edit) => edit,
+            None => return Ok(false),
+        };
+        self.redo.push(edit.apply(scene)?);
+        Ok(true)
+    }
+    // false when there was nothing to redo; failed edits are dropped as for
+    // undo
+    pub fn redo(&mut self, scene: &mut SceneState) -> Result<bool, String> {
+        let edit = match self.redo.pop() {
+            Some(edit) => edit,
+            None => return Ok(false),
+        };
+        // redo only holds what came off undo, so this stays in bounds
+        self.undo.push_back(edit.apply(scene)?);
+        Ok(true)
+    }
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::level::fixtures;
+
+    #[test]
+    fn undoing_and_redoing_a_move() {
+        let data = fixtures::level_data(256, 256, &fixtures::stamp("square", 0., 0., 64));
+        let mut scene = fixtures::scene(&data, 1);
+        let mut history = History::default();
+        let mut transform = scene.svg().stamps[0].transform.clone();
+        transform.tx += 100.;
+        history.apply(&mut scene, Edit::Transform{index:0, transform}).unwrap();
+        assert_eq!(scene.svg().stamps[0].transform.tx, 100.);
+        assert!(history.undo(&mut scene).unwrap());
+        assert_eq!(scene.svg().stamps[0].transform.tx, 0.);
+        assert!(!history.undo(&mut scene).unwrap());
+        assert!(history.redo(&mut scene).unwrap());
+        assert_eq!(scene.svg().stamps[0].transform.tx, 100.);
+        assert!(!history.redo(&mut scene).unwrap());
+        // and undoable again
+        assert!(history.undo(&mut scene).unwrap());
+        assert_eq!(scene.svg().stamps[0].transform.tx, 0.);
+    }
+
+    #[test]
+    fn a_failed_undo_leaves_the_edits_before_it() {
+        // stamps whose outlines can't be loaded, so putting one back fails
+        let stamps = [
+            fixtures::stamp("missing", 0., 0., 64),
+            fixtures::stamp("missing", 100., 0., 64),
+        ].concat();
+        let data = fixtures::level_data(256, 256, &stamps);
+        let mut scene = SceneState::new(fixtures::level(&data), 1);
+        let mut history = History::default();
+        let fill = scene.svg().stamps[0].rect.fill;
+        history.apply(&mut scene, Edit::Recolor{index:0, fill:Color{r:1, g:2, b:3}}).unwrap();
+        history.apply(&mut scene, Edit::Remove{marker:false, index:1}).unwrap();
+        assert!(history.undo(&mut scene).is_err());
+        assert_eq!(scene.svg().stamps.len(), 1);
+        // the recolor is next, not the failed edit again
+        assert!(history.undo(&mut scene).unwrap());
+        assert_eq!(scene.svg().stamps[0].rect.fill, fill);
+        assert!(!history.undo(&mut scene).unwrap());
+    }
+}
@@ -0,0 +1,114 @@
+// device independent controls: the frontends translate keys, pads and touches into these
+
+#[derive(Clone,Copy,Debug,PartialEq,Eq,Hash)]
+pub enum Actor {
+    Cat,
+    Mouse,
+}
+
+pub const ACTORS: [Actor; 2] = [Actor::Cat, Actor::Mouse];
+
+#[derive(Clone,Copy,Debug,PartialEq,Eq,Hash)]
+pub enum Action {
+    Left,
+    Right,
+    Jump,
+    Down,
+}
+
+pub const ACTIONS: [Action; 4] = [Action::Left, Action::Right, Action::Jump, Action::Down];
+
+impl Action {
+    fn bit(self) -> u8 {
+        match self {
+            Action::Left => 1,
+            Action::Right => 2,
+            Action::Jump => 4,
+            Action::Down => 8,
+        }
+    }
+}
+
+// the most an analog stick can be pushed either way
+pub const STICK_MAX: i8 = 127;
+
+// the set of actions one actor is holding during a tick, along with how far
+// an analog stick is pushed sideways; the stick is kept as a small integer
+// so replays reproduce it exactly
+#[derive(Clone,Copy,Debug,Default,PartialEq,Eq)]
+pub struct Actions {
+    bits: u8,
+    stick: i8,
+}
+
+impl Actions {
+    pub fn contains(&self, action: Action) -> bool {
+        self.bits & action.bit() != 0
+    }
+    pub fn insert(&mut self, action: Action) {
+        self.bits |= action.bit();
+    }
+    pub fn remove(&mut self, action: Action) {
+        self.bits &= !action.bit();
+    }
+    // held buttons of both, and whichever stick is pushed further
+    pub fn union(&self, other: Actions) -> Actions {
+        Actions{
+            bits:self.bits | other.bits,
+            stick:if other.stick.unsigned_abs() > self.stick.unsigned_abs() { other.stick } else { self.stick },
+        }
+    }
+    pub fn bits(&self) -> u8 {
+        self.bits
+    }
+    pub fn from_bits(bits: u8) -> Actions {
+        Actions{bits, stick:0}
+    }
+    pub fn stick(&self) -> i8 {
+        self.stick
+    }
+    // -STICK_MAX is all the way left and STICK_MAX all the way right
+    pub fn set_stick(&mut self, stick: i8) {
+        self.stick = stick.max(-STICK_MAX);
+    }
+    // horizontal direction in [-1, 1]; a stick adds to the buttons, so either
+    // can steer on its own
+    pub fn horizontal(&self) -> f32 {
+        let mut ret = f32::from(self.stick) / f32::from(STICK_MAX);
+        if self.contains(Action::Left) {
+            ret -= 1.;
+        }
+        if self.contains(Action::Right) {
+            ret += 1.;
+        }
+        ret.clamp(-1., 1.)
+    }
+}
+
+// everything the simulation needs from the players for one tick
+#[derive(Clone,Copy,Debug,Default,PartialEq,Eq)]
+pub struct Input {
+    pub cat: Actions,
+    pub mouse: Actions,
+}
+
+impl Input {
+    pub fn actions(&self, actor: Actor) -> Actions {
+        match actor {
+            Actor::Cat => self.cat,
+            Actor::Mouse => self.mouse,
+        }
+    }
+    pub fn actions_mut(&mut self, actor: Actor) -> &mut Actions {
+        match actor {
+            Actor::Cat => &mut self.cat,
+            Actor::Mouse => &mut self.mouse,
+        }
+    }
+    pub fn union(&self, other: &Input) -> Input {
+        Input{
+            cat:self.cat.union(other.cat),
+            mouse:self.mouse.union(other.mouse),
+        }
+    }
+}
@@ -0,0 +1,180 @@
+use art_stamps::{Transform, SVG, F64Point, Color, g};
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+use super::game::center;
+
+// stamps named after these files mark spawn points and goals instead of geometry,
+// e.g. <rect ... mask="url(#assets/stamps/goal.bmp)"/>
+pub const CAT_SPAWN_STAMP: &str = "cat_spawn";
+pub const MOUSE_SPAWN_STAMP: &str = "mouse_spawn";
+pub const GOAL_STAMP: &str = "goal";
+
+pub struct Level {
+    pub svg: SVG,
+    // centers of the spawn markers, if the level has them
+    pub cat_spawn: Option<F64Point>,
+    pub mouse_spawn: Option<F64Point>,
+    // regions the mouse escapes through
+    pub goals: Vec<[F64Point;4]>,
+    // marker stamps removed from svg, kept so the level can be written back out
+    pub markers: Vec<g>,
+}
+
+impl Level {
+    pub fn from_svg(mut svg: SVG) -> Self {
+        let mut level = Level{
+            svg:SVG::new(svg.width, svg.height),
+            cat_spawn:None,
+            mouse_spawn:None,
+            goals:Vec::new(),
+            markers:Vec::new(),
+        };
+        for stamp in svg.stamps.drain(..) {
+            if is_marker(&stamp) {
+                level.markers.push(stamp);
+            } else {
+                level.svg.stamps.push(stamp);
+            }
+        }
+        let (cat_spawn, mouse_spawn, goals) = read_markers(&level.markers);
+        level.cat_spawn = cat_spawn;
+        level.mouse_spawn = mouse_spawn;
+        level.goals = goals;
+        level.svg.version = svg.version;
+        level.svg.defs = svg.defs;
+        level
+    }
+}
+
+// the cat spawn, mouse spawn and goals that markers put down; later spawn
+// markers win over earlier ones
+pub fn read_markers(markers: &[g]) -> (Option<F64Point>, Option<F64Point>, Vec<[F64Point;4]>) {
+    let (mut cat_spawn, mut mouse_spawn, mut goals) = (None, None, Vec::new());
+    for marker in markers {
+        match marker_kind(&marker.rect.href.url) {
+            Some(CAT_SPAWN_STAMP) => cat_spawn = Some(center(&marker.transform)),
+            Some(MOUSE_SPAWN_STAMP) => mouse_spawn = Some(center(&marker.transform)),
+            Some(GOAL_STAMP) => goals.push(marker.transform.to_bbox()),
+            _ => {},
+        }
+    }
+    (cat_spawn, mouse_spawn, goals)
+}
+
+pub fn is_marker(stamp: &g) -> bool {
+    marker_kind(&stamp.rect.href.url).is_some()
+}
+
+// a stamp of the image at url, sized by transform; art_stamps only makes
+// these through SVG::add
+pub fn new_stamp(transform: Transform, url: &str, fill: Color) -> g {
+    let mut svg = SVG::new(0, 0);
+    svg.add(transform, url.to_string(), String::new(), fill);
+    svg.stamps.remove(0)
+}
+
+// g has no Clone of its own
+pub fn copy_stamp(stamp: &g) -> g {
+    let mut copy = new_stamp(stamp.transform.clone(), &stamp.rect.href.url, stamp.rect.fill);
+    copy.rect.x = stamp.rect.x;
+    copy.rect.y = stamp.rect.y;
+    copy.rect.width = stamp.rect.width;
+    copy.rect.height = stamp.rect.height;
+    copy.rect.href.clip = stamp.rect.href.clip.clone();
+    copy
+}
+
+// the level as an svg file again, the way art_stamps writes them, markers
+// included; each stamp's outline is embedded as the mask named in its href,
+// read from assets_dir, so the file also shows up in svg viewers
+pub fn write_level(svg: &SVG, markers: &[g], assets_dir: &Path) -> Result<String, String> {
+    let mut out = String::new();
+    writeln!(out, "<svg version=\"{}\" width=\"{}\" height=\"{}\" xmlns=\"http://www.w3.org/2000/svg\">", svg.version, svg.width, svg.height).unwrap();
+    for stamp in svg.stamps.iter().chain(markers.iter()) {
+        let transform = stamp.transform.to_string().map_err(|err| format!("{:?}", err))?;
+        let clip = if stamp.rect.href.clip.is_empty() {
+            String::new()
+        } else {
+            format!(" clip-path=\"{}\"", escape(&stamp.rect.href.clip))
+        };
+        writeln!(out, "<g transform=\"{}\">\n<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\" mask=\"url(#{})\"{}/>\n</g>",
+                 transform, stamp.rect.x, stamp.rect.y, stamp.rect.width, stamp.rect.height,
+                 stamp.rect.fill.to_string(), escape(&stamp.rect.href.url), clip).unwrap();
+    }
+    writeln!(out, "<defs>").unwrap();
+    for clip in svg.defs.clipPath.iter() {
+        let points: Vec<String> = clip.polygon.points.iter().map(|point| format!("{} {}", point.0, point.1)).collect();
+        writeln!(out, "<clipPath id=\"{}\">\n<polygon points=\"{}\"/>\n</clipPath>", escape(&clip.id), points.join(",")).unwrap();
+    }
+    let mut urls: Vec<&str> = svg.stamps.iter().map(|stamp| stamp.rect.href.url.as_str()).collect();
+    urls.sort_unstable();
+    urls.dedup();
+    for url in urls {
+        let name = Path::new(stamp_file_name(url)).with_extension("svg");
+        let outline_path = assets_dir.join(&name);
+        let outline = fs::read_to_string(&outline_path).map_err(|err| format!("{}: {}", outline_path.display(), err))?;
+        writeln!(out, "<mask id=\"{}\">{}</mask>", escape(url), outline).unwrap();
+    }
+    writeln!(out, "</defs>\n</svg>").unwrap();
+    Ok(out)
+}
+
+fn escape(attribute: &str) -> String {
+    attribute.replace('&', "&amp;").replace('"', "&quot;").replace('<', "&lt;")
+}
+
+// levels refer to stamps as e.g. "assets/stamps/rect.bmp"; only the file name is
+// used to find the stamp, so the asset directory can live anywhere
+pub fn stamp_file_name(url: &str) -> &str {
+    url.rsplit('/').next().unwrap_or(url)
+}
+
+fn marker_kind(url: &str) -> Option<&str> {
+    let stem = Path::new(url).file_stem()?.to_str()?;
+    match stem {
+        CAT_SPAWN_STAMP | MOUSE_SPAWN_STAMP | GOAL_STAMP => Some(stem),
+        _ => None,
+    }
+}
+
+// a transform of the given size whose center sits on point
+pub fn centered_at(point: F64Point, width: u32, height: u32) -> Transform {
+    let mut ret = Transform::new(width, height);
+    ret.tx = point.0 - ret.midx;
+    ret.ty = point.1 - ret.midy;
+    ret
+}
+
+// levels for the tests, written out the way art_stamps reads them
+#[cfg(test)]
+pub mod fixtures {
+    use art_stamps::SVG;
+    use std::path::Path;
+    use super::super::game::SceneState;
+    use super::Level;
+
+    // the named stamp of the shipped assets, size pixels square with its
+    // top left corner at x, y
+    pub fn stamp(name: &str, x: f64, y: f64, size: u32) -> String {
+        let half = size / 2;
+        format!("<g transform=\"translate({}, {}) translate({}, {}) translate(-{}, -{})\">\n<rect x=\"0\" y=\"0\" width=\"{}\" height=\"{}\" fill=\"#000000\" mask=\"url(#assets/stamps/{}.bmp)\"/>\n</g>\n",
+                x, y, half, half, half, half, size, size, name)
+    }
+
+    // the file of a level made of stamps
+    pub fn level_data(width: u32, height: u32, stamps: &str) -> String {
+        format!("<svg version=\"2.0\" width=\"{}\" height=\"{}\" xmlns=\"http://www.w3.org/2000/svg\">\n{}<defs>\n</defs>\n</svg>\n", width, height, stamps)
+    }
+
+    pub fn level(level_data: &str) -> Level {
+        Level::from_svg(SVG::from_str(level_data).unwrap())
+    }
+
+    // a scene of the level with its outlines loaded from the shipped assets
+    pub fn scene(level_data: &str, seed: u64) -> SceneState {
+        let mut scene = SceneState::new(level(level_data), seed);
+        scene.load_polygons(Path::new("assets")).unwrap();
+        scene
+    }
+}
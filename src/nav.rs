@@ -0,0 +1,362 @@
+// where an actor can stand in a level and how it can get from one such place
+// to another, derived from the stamp outlines: surfaces flat enough to stand
+// on are sampled into nodes, joined by walking along them, falling off them
+// and jumping between them
+use art_stamps::F64Point;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use super::collision::{self, Aabb};
+use super::game::{GRAVITY, JUMP_SPEED, MAX_SPEED, SLIDE_NORMAL_Y};
+use super::grid::ShapeGrid;
+
+// horizontal distance between nodes
+const NODE_SPACING: f64 = 16.0;
+// sizes of the square bodies nodes and moves are checked for; their
+// clearance is the largest that fits
+const CLEARANCE_STEPS: [f64; 4] = [16.0, 32.0, 64.0, 128.0];
+// how many columns over a walk may reach
+const WALK_COLUMNS: usize = 2;
+// how far below its feet a body may stand on something and still walk across it
+const WALK_LIFT: f64 = 4.0;
+// jump and fall arcs keep the feet this far off the surfaces they leave and land on
+const ARC_LIFT: f64 = 2.0;
+const ARC_SAMPLES: usize = 12;
+// planned arcs leave some of the actors' speed and jump height spare, so that
+// small errors in following them can be made up for in the air
+const ARC_SPEED: f64 = 0.95;
+const ARC_RISE: f64 = 0.9;
+// jumping is slower and riskier than walking the same distance
+const JUMP_COST: f64 = 48.0;
+
+#[derive(Clone,Copy,Debug,PartialEq,Eq)]
+pub enum NavMove {
+    Walk,
+    // walking off a ledge, without jumping
+    Fall,
+    Jump,
+}
+
+#[derive(Clone,Copy,Debug)]
+pub struct NavNode {
+    // a point on the surface, where a standing body's feet are
+    pub position: F64Point,
+    // rise over run of the surface
+    pub slope: f64,
+    // the largest body that can stand there
+    pub clearance: f64,
+}
+
+#[derive(Clone,Copy,Debug)]
+pub struct NavEdge {
+    pub to: usize,
+    pub kind: NavMove,
+    pub cost: f64,
+    // how fast the body has to move sideways to make it
+    pub speed: f64,
+    // the tallest body that can make the move
+    pub clearance: f64,
+}
+
+pub struct NavGraph {
+    nodes: Vec<NavNode>,
+    // outgoing edges of each node
+    edges: Vec<Vec<NavEdge>>,
+}
+
+impl NavGraph {
+    pub fn build(grid: &ShapeGrid, width: u32, height: u32) -> Self {
+        let mut graph = NavGraph{
+            nodes:Vec::new(),
+            edges:Vec::new(),
+        };
+        // each column's nodes, top to bottom
+        let mut columns = Vec::new();
+        let mut x = NODE_SPACING * 0.5;
+        while x < f64::from(width) {
+            let first = graph.nodes.len();
+            graph.nodes.extend(column_nodes(grid, x, f64::from(height)));
+            columns.push(first..graph.nodes.len());
+            x += NODE_SPACING;
+        }
+        graph.edges = vec![Vec::new(); graph.nodes.len()];
+        // the next column over, or the one after when there's a crack
+        // between stamps too narrow to fall into
+        for (index, column) in columns.iter().enumerate() {
+            for next in columns[index + 1..].iter().take(WALK_COLUMNS) {
+                for a in column.clone() {
+                    for b in next.clone() {
+                        graph.add_walk(grid, a, b);
+                    }
+                }
+            }
+        }
+        for a in 0..graph.nodes.len() {
+            for b in 0..graph.nodes.len() {
+                if a != b && !graph.edges[a].iter().any(|edge| edge.to == b) {
+                    graph.add_arc(grid, a, b);
+                }
+            }
+        }
+        graph
+    }
+    pub fn nodes(&self) -> &[NavNode] {
+        &self.nodes
+    }
+    pub fn edges(&self, node: usize) -> &[NavEdge] {
+        &self.edges[node]
+    }
+    // the node a body of the given size with its feet at feet is standing on,
+    // or would land on; nodes above the feet are much less likely than ones
+    // below, which the body could drop onto
+    pub fn nearest(&self, feet: F64Point, size: f64) -> Option<usize> {
+        let score = |node: &NavNode| {
+            let dx = node.position.0 - feet.0;
+            let mut dy = node.position.1 - feet.1;
+            if dy < -NODE_SPACING {
+                dy *= 4.;
+            }
+            dx * dx + dy * dy
+        };
+        self.nodes.iter().enumerate()
+            .filter(|&(_, node)| node.clearance >= size)
+            .min_by(|a, b| score(a.1).partial_cmp(&score(b.1)).unwrap_or(Ordering::Equal))
+            .map(|(index, _)| index)
+    }
+    // the cheapest route from one node to another for a body of the given
+    // size that runs no faster than speed, as the nodes after from together
+    // with the move into each; when to can't be reached, the route leads as
+    // close to it as possible instead; passing through a node costs whatever
+    // toll says on top, and an infinite toll rules the node out
+    pub fn path<F: Fn(usize) -> f64>(&self, from: usize, to: usize, size: f64, speed: f64, toll: F) -> Vec<(usize, NavMove)> {
+        let heuristic = |node: usize| distance(self.nodes[node].position, self.nodes[to].position);
+        let mut best = vec![f64::INFINITY; self.nodes.len()];
+        let mut came_from: Vec<Option<(usize, NavMove)>> = vec![None; self.nodes.len()];
+        let mut open = BinaryHeap::new();
+        best[from] = 0.;
+        open.push(Candidate{estimate:heuristic(from), node:from});
+        let mut closest = from;
+        while let Some(Candidate{node, ..}) = open.pop() {
+            if heuristic(node) < heuristic(closest) {
+                closest = node;
+            }
+            if node == to {
+                break;
+            }
+            for edge in self.edges[node].iter().filter(|edge| edge.clearance >= size && edge.speed <= speed) {
+                let cost = best[node] + edge.cost + toll(edge.to);
+                if cost < best[edge.to] {
+                    best[edge.to] = cost;
+                    came_from[edge.to] = Some((node, edge.kind));
+                    open.push(Candidate{estimate:cost + heuristic(edge.to), node:edge.to});
+                }
+            }
+        }
+        let mut ret = Vec::new();
+        let mut cur = closest;
+        while let Some((prev, kind)) = came_from[cur] {
+            ret.push((cur, kind));
+            cur = prev;
+        }
+        ret.reverse();
+        ret
+    }
+    // the cost of the cheapest route from one node to every other for a body
+    // of the given size that runs no faster than speed; infinite for nodes it
+    // can't reach; toll is as for path
+    pub fn costs<F: Fn(usize) -> f64>(&self, from: usize, size: f64, speed: f64, toll: F) -> Vec<f64> {
+        let mut best = vec![f64::INFINITY; self.nodes.len()];
+        let mut open = BinaryHeap::new();
+        best[from] = 0.;
+        open.push(Candidate{estimate:0., node:from});
+        while let Some(Candidate{estimate, node}) = open.pop() {
+            if estimate > best[node] {
+                continue;
+            }
+            for edge in self.edges[node].iter().filter(|edge| edge.clearance >= size && edge.speed <= speed) {
+                let cost = best[node] + edge.cost + toll(edge.to);
+                if cost < best[edge.to] {
+                    best[edge.to] = cost;
+                    open.push(Candidate{estimate:cost, node:edge.to});
+                }
+            }
+        }
+        best
+    }
+    // neighbouring nodes joined by a surface that a body can walk along
+    fn add_walk(&mut self, grid: &ShapeGrid, a: usize, b: usize) {
+        let (from, to) = (self.nodes[a], self.nodes[b]);
+        if (to.position.1 - from.position.1).abs() > NODE_SPACING || self.edges[a].iter().any(|edge| edge.to == b) {
+            return;
+        }
+        let lifted = |point: F64Point, lift: f64| (point.0, point.1 - lift);
+        let mut bounds = Aabb::around(&[from.position, to.position]);
+        bounds.min.1 -= from.clearance.max(to.clearance);
+        let shapes = grid.near(&bounds);
+        let clear = |lift: f64| collision::segment_clear(&shapes, lifted(from.position, lift), lifted(to.position, lift));
+        if !clear(WALK_LIFT) {
+            return;
+        }
+        // the head has to get through too
+        let clearance = match fitting(from.clearance.min(to.clearance), clear) {
+            Some(clearance) => clearance,
+            None => return,
+        };
+        let cost = distance(from.position, to.position);
+        self.edges[a].push(NavEdge{to:b, kind:NavMove::Walk, cost, speed:0., clearance});
+        self.edges[b].push(NavEdge{to:a, kind:NavMove::Walk, cost, speed:0., clearance});
+    }
+    // a fall or, failing that, a jump from a to b, if either is within reach
+    // and the body's feet and head both stay clear along the way; a jump
+    // blocked by a ceiling is tried again as one that bumps into it and comes
+    // down early, lower each time
+    fn add_arc(&mut self, grid: &ShapeGrid, a: usize, b: usize) {
+        let (from, to) = (self.nodes[a], self.nodes[b]);
+        let gravity = f64::from(GRAVITY);
+        let dx = to.position.0 - from.position.0;
+        let rise = from.position.1 - to.position.1;
+        let fall_time = if rise < 0. {(-2. * rise / gravity).sqrt()} else {0.};
+        if rise < 0. && dx.abs() <= f64::from(MAX_SPEED) * ARC_SPEED * fall_time {
+            if let Some(edge) = self.arc(grid, a, b, NavMove::Fall, 0.) {
+                self.edges[a].push(edge);
+            }
+            return;
+        }
+        let jump_speed = f64::from(JUMP_SPEED);
+        let mut height = jump_speed * jump_speed / (2. * gravity);
+        let mut best: Option<NavEdge> = None;
+        while rise <= height * ARC_RISE && height > 0. {
+            if let Some(edge) = self.arc(grid, a, b, NavMove::Jump, height) {
+                if best.is_none_or(|best| edge.clearance > best.clearance) {
+                    best = Some(edge);
+                }
+                if edge.clearance >= from.clearance.min(to.clearance) {
+                    break;
+                }
+            }
+            height -= NODE_SPACING;
+        }
+        if let Some(edge) = best {
+            self.edges[a].push(edge);
+        }
+    }
+    // the move from a to b rising height above the takeoff before coming
+    // down, or just falling when height is 0, if it's within reach and clear
+    fn arc(&self, grid: &ShapeGrid, a: usize, b: usize, kind: NavMove, height: f64) -> Option<NavEdge> {
+        let (from, to) = (self.nodes[a], self.nodes[b]);
+        let gravity = f64::from(GRAVITY);
+        let jump_speed = if kind == NavMove::Jump {f64::from(JUMP_SPEED)} else {0.};
+        let dx = to.position.0 - from.position.0;
+        let rise = from.position.1 - to.position.1;
+        // rising until height, where the top of the jump or a ceiling stops
+        // it, then falling onto b on the way down
+        let rise_time = (jump_speed - (jump_speed * jump_speed - 2. * gravity * height).max(0.).sqrt()) / gravity;
+        let time = rise_time + (2. * (height - rise) / gravity).sqrt();
+        if dx.abs() > f64::from(MAX_SPEED) * ARC_SPEED * time {
+            return None;
+        }
+        let arc: Vec<F64Point> = (0..=ARC_SAMPLES).map(|index| {
+            let t = time * index as f64 / ARC_SAMPLES as f64;
+            let up = if t < rise_time {jump_speed * t - 0.5 * gravity * t * t} else {height - 0.5 * gravity * (t - rise_time).powi(2)};
+            (from.position.0 + dx * t / time, from.position.1 - ARC_LIFT - up)
+        }).collect();
+        let largest = from.clearance.min(to.clearance);
+        let bounds = Aabb::around(&arc).expanded(largest);
+        let shapes = grid.near(&bounds);
+        let clear = |offset: F64Point| arc.windows(2).all(
+            |pair| collision::segment_clear(&shapes, (pair[0].0 + offset.0, pair[0].1 + offset.1), (pair[1].0 + offset.0, pair[1].1 + offset.1)));
+        if !clear((0., 0.)) {
+            return None;
+        }
+        // the corners of the body, which rests higher on slopes
+        let slope = from.slope.abs().max(to.slope.abs());
+        let body_clear = |size: f64| {
+            let bottom = -size * 0.5 * slope;
+            [(-size * 0.5, bottom), (size * 0.5, bottom), (-size * 0.5, bottom - size), (size * 0.5, bottom - size)]
+                .iter().all(|&corner| clear(corner))
+        };
+        let clearance = fitting(largest, body_clear)?;
+        let mut cost = distance(from.position, to.position);
+        if kind == NavMove::Jump {
+            cost += JUMP_COST;
+        }
+        Some(NavEdge{to:b, kind, cost, speed:dx.abs() / time, clearance})
+    }
+}
+
+// the largest of CLEARANCE_STEPS up to limit that fits, checking each size
+// only if the ones below it fit
+fn fitting<F: Fn(f64) -> bool>(limit: f64, fits: F) -> Option<f64> {
+    CLEARANCE_STEPS.iter().cloned()
+        .take_while(|&size| size <= limit && fits(size))
+        .last()
+}
+
+// the places along the vertical line at x where open space sits on top of a
+// surface flat enough to stand on, with how large a body can stand on each
+fn column_nodes(grid: &ShapeGrid, x: f64, height: f64) -> Vec<NavNode> {
+    let largest = CLEARANCE_STEPS[CLEARANCE_STEPS.len() - 1];
+    let column = Aabb{min:(x - 1., -largest), max:(x + 1., height)};
+    let shapes = grid.near(&column);
+    // where the line crosses an outline, and that edge's slope
+    let mut crossings = Vec::new();
+    for shape in shapes.iter() {
+        let mut last = shape.outline[shape.outline.len() - 1];
+        for &cur in shape.outline.iter() {
+            if (cur.0 > x) != (last.0 > x) {
+                let slope = (cur.1 - last.1) / (cur.0 - last.0);
+                crossings.push((last.1 + slope * (x - last.0), slope));
+            }
+            last = cur;
+        }
+    }
+    crossings.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+    // the steepest slope of ground with a normal at SLIDE_NORMAL_Y
+    let max_slope = (1. - f64::from(SLIDE_NORMAL_Y).powi(2)).sqrt() / -f64::from(SLIDE_NORMAL_Y);
+    let solid = |y: f64| shapes.iter().any(|shape| shape.contains((x, y)));
+    let mut ret = Vec::new();
+    for &(y, slope) in crossings.iter() {
+        if slope.abs() > max_slope || y < 0. || solid(y - ARC_LIFT) || !solid(y + ARC_LIFT) {
+            continue;
+        }
+        // a body standing on a slope rests on its uphill corner
+        let standing = |size: f64| {
+            let bottom = y - ARC_LIFT - size * 0.5 * slope.abs();
+            let body = Aabb{min:(x - size * 0.5, bottom - size), max:(x + size * 0.5, bottom)};
+            collision::box_clear(&grid.near(&body), &body)
+        };
+        if let Some(clearance) = fitting(largest, standing) {
+            ret.push(NavNode{position:(x, y), slope, clearance});
+        }
+    }
+    ret
+}
+
+fn distance(a: F64Point, b: F64Point) -> f64 {
+    ((a.0 - b.0) * (a.0 - b.0) + (a.1 - b.1) * (a.1 - b.1)).sqrt()
+}
+
+// an entry of the open set, ordered so the heap pops the lowest estimate first
+struct Candidate {
+    estimate: f64,
+    node: usize,
+}
+
+impl PartialEq for Candidate {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.estimate.partial_cmp(&self.estimate).unwrap_or(Ordering::Equal)
+    }
+}
@@ -0,0 +1,214 @@
+// per tick input recordings, so a session can be re-simulated exactly
+use std::fmt;
+use super::input::{Actions, Input};
+use super::game::SceneState;
+
+const REPLAY_HEADER: &str = "mousegame-replay 2";
+// replays from before analog sticks, which lack the stick columns
+const REPLAY_HEADER_V1: &str = "mousegame-replay 1";
+const ACTOR_COLLISION_LINE: &str = "actors push";
+
+pub struct Replay {
+    // path of the level the recording was made on
+    pub level: String,
+    pub level_hash: u64,
+    pub seed: u64,
+    // whether the actors blocked and pushed each other
+    pub actor_collision: bool,
+    pub inputs: Vec<Input>,
+}
+
+impl Replay {
+    pub fn new(level: &str, level_data: &str, seed: u64) -> Self {
+        Replay{
+            level:level.to_string(),
+            level_hash:level_hash(level_data),
+            seed,
+            actor_collision:false,
+            inputs:Vec::new(),
+        }
+    }
+    pub fn record(&mut self, input: &Input) {
+        self.inputs.push(*input);
+    }
+    // errors out if level_data is not the level this replay was recorded on
+    pub fn check_level(&self, level_data: &str) -> Result<(), String> {
+        let hash = level_hash(level_data);
+        if hash != self.level_hash {
+            return Err(format!("replay was recorded on {} with hash {:016x}, but the level has hash {:016x}",
+                               self.level, self.level_hash, hash));
+        }
+        Ok(())
+    }
+    // runs every recorded tick through scene, which must be freshly made from the same level and seed
+    pub fn play(&self, scene: &mut SceneState) -> Result<(), String> {
+        scene.set_actor_collision(self.actor_collision);
+        for input in self.inputs.iter() {
+            scene.sim(input)?;
+        }
+        Ok(())
+    }
+}
+
+// one line per run of identical ticks:
+// "<count> <cat actions> <cat stick> <mouse actions> <mouse stick>"
+impl fmt::Display for Replay {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}", REPLAY_HEADER)?;
+        writeln!(f, "level {}", self.level)?;
+        writeln!(f, "hash {:016x}", self.level_hash)?;
+        writeln!(f, "seed {}", self.seed)?;
+        if self.actor_collision {
+            writeln!(f, "{}", ACTOR_COLLISION_LINE)?;
+        }
+        let mut index = 0;
+        while index < self.inputs.len() {
+            let input = self.inputs[index];
+            let count = self.inputs[index..].iter().take_while(|other| **other == input).count();
+            writeln!(f, "{} {:x} {} {:x} {}", count, input.cat.bits(), input.cat.stick(), input.mouse.bits(), input.mouse.stick())?;
+            index += count;
+        }
+        Ok(())
+    }
+}
+
+impl std::str::FromStr for Replay {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, String> {
+        let mut lines = s.lines().peekable();
+        let columns = match lines.next() {
+            Some(REPLAY_HEADER) => 5,
+            Some(REPLAY_HEADER_V1) => 3,
+            _ => return Err("not a mousegame replay".to_string()),
+        };
+        let level = header_field(lines.next(), "level")?.to_string();
+        let level_hash = u64::from_str_radix(header_field(lines.next(), "hash")?, 16).map_err(|err| format!("bad replay hash: {}", err))?;
+        let seed = header_field(lines.next(), "seed")?.parse::<u64>().map_err(|err| format!("bad replay seed: {}", err))?;
+        // optional, as replays from before actor collision lack it
+        let actor_collision = lines.peek() == Some(&ACTOR_COLLISION_LINE);
+        if actor_collision {
+            lines.next();
+        }
+        let mut inputs = Vec::new();
+        for line in lines {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.is_empty() {
+                continue;
+            }
+            if fields.len() != columns {
+                return Err(format!("bad replay line: {}", line));
+            }
+            let count = fields[0].parse::<usize>().map_err(|err| format!("bad replay line {}: {}", line, err))?;
+            // the actions then, in newer replays, the stick of each actor
+            let actions = |field: usize| -> Result<Actions, String> {
+                let bits = u8::from_str_radix(fields[field], 16).map_err(|err| format!("bad replay line {}: {}", line, err))?;
+                let mut actions = Actions::from_bits(bits);
+                if columns == 5 {
+                    actions.set_stick(fields[field + 1].parse::<i8>().map_err(|err| format!("bad replay line {}: {}", line, err))?);
+                }
+                Ok(actions)
+            };
+            let stride = (columns - 1) / 2;
+            let input = Input{cat:actions(1)?, mouse:actions(1 + stride)?};
+            inputs.extend(std::iter::repeat_n(input, count));
+        }
+        Ok(Replay{
+            level,
+            level_hash,
+            seed,
+            actor_collision,
+            inputs,
+        })
+    }
+}
+
+fn header_field<'a>(line: Option<&'a str>, name: &str) -> Result<&'a str, String> {
+    match line {
+        Some(line) if line.starts_with(name) && line[name.len()..].starts_with(' ') => Ok(&line[name.len() + 1..]),
+        _ => Err(format!("replay is missing its {} line", name)),
+    }
+}
+
+// FNV-1a, which unlike the std hashers is stable across builds
+pub fn level_hash(level_data: &str) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for byte in level_data.bytes() {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::input::{Actor, Action};
+    use super::super::level::fixtures::scene;
+
+    // a few seconds of the mouse running right and jumping, with the cat
+    // on a half pushed stick
+    fn recording(level_data: &str) -> Replay {
+        let mut replay = Replay::new("assets/level.svg", level_data, 42);
+        replay.actor_collision = true;
+        for tick in 0..600 {
+            let mut input = Input::default();
+            input.mouse.insert(Action::Right);
+            if tick % 90 < 10 {
+                input.mouse.insert(Action::Jump);
+            }
+            input.cat.set_stick(-64);
+            replay.record(&input);
+        }
+        replay
+    }
+
+    #[test]
+    fn round_trips_through_text() {
+        let replay = recording("<svg/>");
+        let parsed = replay.to_string().parse::<Replay>().unwrap();
+        assert_eq!(parsed.level, replay.level);
+        assert_eq!(parsed.level_hash, replay.level_hash);
+        assert_eq!(parsed.seed, 42);
+        assert!(parsed.actor_collision);
+        assert_eq!(parsed.inputs, replay.inputs);
+    }
+
+    #[test]
+    fn reads_replays_from_before_sticks() {
+        let replay = "mousegame-replay 1\nlevel a.svg\nhash 00000000000000ff\nseed 7\n3 1 6\n";
+        let parsed = replay.parse::<Replay>().unwrap();
+        assert_eq!(parsed.level_hash, 0xff);
+        assert!(!parsed.actor_collision);
+        assert_eq!(parsed.inputs.len(), 3);
+        assert!(parsed.inputs.iter().all(|input| input.cat.contains(Action::Left) && input.mouse.contains(Action::Jump)));
+        assert_eq!(parsed.inputs[0].actions(Actor::Mouse).stick(), 0);
+    }
+
+    #[test]
+    fn rejects_what_it_cant_read() {
+        assert!("".parse::<Replay>().is_err());
+        assert!("mousegame-replay 2\nlevel a.svg\nhash zz\nseed 7\n".parse::<Replay>().is_err());
+        assert!("mousegame-replay 2\nlevel a.svg\nhash 0\nseed 7\n3 1 6\n".parse::<Replay>().is_err());
+        let replay = recording("one level");
+        assert!(replay.check_level("one level").is_ok());
+        assert!(replay.check_level("another level").is_err());
+    }
+
+    #[test]
+    fn plays_back_the_same_game() {
+        let level_data = std::fs::read_to_string("assets/level.svg").unwrap();
+        let replay = recording(&level_data);
+        let mut recorded = scene(&level_data, replay.seed);
+        recorded.set_actor_collision(true);
+        for input in replay.inputs.iter() {
+            recorded.sim(input).unwrap();
+        }
+        let parsed = replay.to_string().parse::<Replay>().unwrap();
+        let mut played = scene(&level_data, parsed.seed);
+        parsed.play(&mut played).unwrap();
+        for &actor in [Actor::Cat, Actor::Mouse].iter() {
+            assert_eq!(played.actor(actor).center(), recorded.actor(actor).center());
+        }
+        assert_eq!(played.round(), recorded.round());
+    }
+}